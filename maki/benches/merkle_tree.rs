@@ -0,0 +1,61 @@
+//! Compares inserting a batch of leaves with `insert_leaves` and one by one with `insert_leaf`.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use maki::merkle_tree::MerkleTree;
+
+const BENCH_TREE_DEPTH: u8 = 10;
+
+const BATCH_SIZES: [usize; 3] = [8, 32, 128];
+
+fn bench_insertion<const ARITY: usize>(c: &mut Criterion, name: &str) {
+    let mut group = c.benchmark_group(name);
+
+    for batch_size in BATCH_SIZES {
+        let leaves: Vec<[u8; 32]> = (0..batch_size).map(|i| [i as u8; 32]).collect();
+
+        group.bench_with_input(
+            BenchmarkId::new("insert_leaf", batch_size),
+            &leaves,
+            |b, leaves| {
+                b.iter_batched(
+                    || MerkleTree::<ARITY>::new(BENCH_TREE_DEPTH).unwrap(),
+                    |mut tree| {
+                        for leaf in leaves {
+                            tree.insert_leaf(black_box(*leaf)).unwrap();
+                        }
+                        tree
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("insert_leaves", batch_size),
+            &leaves,
+            |b, leaves| {
+                b.iter_batched(
+                    || MerkleTree::<ARITY>::new(BENCH_TREE_DEPTH).unwrap(),
+                    |mut tree| {
+                        tree.insert_leaves(black_box(leaves)).unwrap();
+                        tree
+                    },
+                    BatchSize::SmallInput,
+                )
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn binary_tree_insertion(c: &mut Criterion) {
+    bench_insertion::<2>(c, "binary_tree_insertion");
+}
+
+fn quinary_tree_insertion(c: &mut Criterion) {
+    bench_insertion::<5>(c, "quinary_tree_insertion");
+}
+
+criterion_group!(benches, binary_tree_insertion, quinary_tree_insertion);
+criterion_main!(benches);
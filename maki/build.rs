@@ -0,0 +1,58 @@
+//! Generates the zero values of the Merkle trees.
+//!
+//! The empty leaf is the nothing-up-my-sleeve value `BLAKE2b-512("maki")` reduced in the scalar field,
+//! the zero value of each level is the Poseidon hash of the children of the level below, all being zero values.
+
+use std::{env, fmt::Write, fs, path::Path};
+
+use blake2::{Blake2b512, Digest};
+use dusk_bls12_381::BlsScalar;
+use dusk_poseidon::sponge;
+
+/// Must match `MERKLE_TREE_MAX_DEPTH` in `src/merkle_tree.rs`, the generated tables would not compile otherwise.
+const MERKLE_TREE_MAX_DEPTH: usize = 32;
+
+const ZEROS_SEED: &[u8] = b"maki";
+
+fn main() {
+    let mut generated = String::from("// Generated by build.rs, do not edit.\n");
+
+    generated.push_str(&zeros_table("MERKLE_TREE_BINARY_ZEROS", 2));
+    generated.push_str(&zeros_table("MERKLE_TREE_QUINARY_ZEROS", 5));
+
+    let out_dir = env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+    fs::write(Path::new(&out_dir).join("merkle_tree_zeros.rs"), generated)
+        .expect("failed to write the Merkle tree zeros");
+
+    println!("cargo:rerun-if-changed=build.rs");
+}
+
+fn zeros_table(name: &str, arity: usize) -> String {
+    let mut zero = BlsScalar::from_bytes_wide(&Blake2b512::digest(ZEROS_SEED).into());
+
+    let mut table = format!(
+        "\nconst {}: [[u8; 32]; MERKLE_TREE_MAX_DEPTH + 1] = [\n",
+        name
+    );
+
+    for _ in 0..=MERKLE_TREE_MAX_DEPTH {
+        writeln!(table, "    {:?},", scalar_to_bytes(&zero)).unwrap();
+
+        zero = sponge::hash(&vec![zero; arity]);
+    }
+
+    table.push_str("];\n");
+
+    table
+}
+
+/// Same encoding as `maki_shared::functions_utils::scalar_to_bytes`, the Montgomery limbs in big-endian.
+fn scalar_to_bytes(scalar: &BlsScalar) -> [u8; 32] {
+    let mut bytes = [0; 32];
+
+    for (i, limb) in scalar.internal_repr().iter().enumerate() {
+        bytes[i * 8..(i + 1) * 8].copy_from_slice(&limb.to_be_bytes());
+    }
+
+    bytes
+}
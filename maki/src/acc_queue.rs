@@ -0,0 +1,406 @@
+use crate::hasher::hash_children;
+#[cfg(feature = "std")]
+use ink::storage::traits::StorageLayout;
+use ink::storage::traits::{ManualKey, Storable, StorableHint, StorageKey};
+use ink::storage::Mapping;
+use maki_shared::types::TreeRoot;
+
+use crate::maki_types::HashedLeaf;
+use crate::merkle_tree::{max_leaves, merkle_tree_zeros, MerkleTreeError, MERKLE_TREE_MAX_DEPTH};
+
+type Subroots<const KEY: u32> = Mapping<u32, TreeRoot, ManualKey<KEY>>;
+
+/// Accumulator queue, a Merkle tree of arity `ARITY` whose leaves are enqueued cheaply and whose root is computed later.
+///
+/// Leaves are accumulated in subtrees of depth `sub_depth`, a group of siblings is only hashed once it is complete.
+/// Once no more leaves are enqueued, the roots of the subtrees are merged into the main tree over as many calls
+/// to `merge_subroots` as needed, then `merge` computes its root. The root is the same as the one of a `MerkleTree`
+/// of the same arity and depth with the same leaves.
+///
+/// The subroots are kept in their own storage cells under the storage key `KEY`, enqueuing a leaf does not load
+/// the subroots already accumulated. Each queue of a contract must be given its own key.
+#[derive(Storable, StorableHint, StorageKey)]
+#[cfg_attr(feature = "std", derive(Debug, scale_info::TypeInfo))]
+pub struct AccQueue<const ARITY: usize, const KEY: u32> {
+    sub_depth: u8,
+    tree_depth: u8,
+    number_of_leaves: u128,
+    /// Siblings of the group being filled at each level, the levels below `sub_depth` accumulate the leaves,
+    /// the levels above accumulate the subroots.
+    levels: [[[u8; 32]; ARITY]; MERKLE_TREE_MAX_DEPTH],
+    /// Number of siblings in the group being filled at each level.
+    level_lengths: [u8; MERKLE_TREE_MAX_DEPTH],
+    subroots: Subroots<KEY>,
+    number_of_subroots: u32,
+    merged_subroots: u32,
+    /// Whether the last subtree has been completed with zeros, no leaf can be enqueued afterwards.
+    filled: bool,
+    root: Option<TreeRoot>,
+}
+
+// Storage layout is not derived, ink! implements it only for arrays of a given size
+#[cfg(feature = "std")]
+impl<const ARITY: usize, const KEY: u32> StorageLayout for AccQueue<ARITY, KEY> {
+    fn layout(key: &ink::primitives::Key) -> ink::metadata::layout::Layout {
+        use ink::metadata::layout::{FieldLayout, Layout, LayoutKey, LeafLayout, StructLayout};
+
+        fn leaf<T: scale_info::TypeInfo + 'static>(key: &ink::primitives::Key) -> Layout {
+            Layout::Leaf(LeafLayout::from_key::<T>(LayoutKey::from(key)))
+        }
+
+        Layout::Struct(StructLayout::new(
+            "AccQueue",
+            [
+                FieldLayout::new("sub_depth", leaf::<u8>(key)),
+                FieldLayout::new("tree_depth", leaf::<u8>(key)),
+                FieldLayout::new("number_of_leaves", leaf::<u128>(key)),
+                FieldLayout::new(
+                    "levels",
+                    leaf::<[[[u8; 32]; ARITY]; MERKLE_TREE_MAX_DEPTH]>(key),
+                ),
+                FieldLayout::new("level_lengths", leaf::<[u8; MERKLE_TREE_MAX_DEPTH]>(key)),
+                FieldLayout::new("subroots", Subroots::<KEY>::layout(key)),
+                FieldLayout::new("number_of_subroots", leaf::<u32>(key)),
+                FieldLayout::new("merged_subroots", leaf::<u32>(key)),
+                FieldLayout::new("filled", leaf::<bool>(key)),
+                FieldLayout::new("root", leaf::<Option<TreeRoot>>(key)),
+            ],
+        ))
+    }
+}
+
+impl<const ARITY: usize, const KEY: u32> AccQueue<ARITY, KEY> {
+    const ZEROS: &'static [[u8; 32]; MERKLE_TREE_MAX_DEPTH + 1] = merkle_tree_zeros(ARITY);
+
+    pub fn new(sub_depth: u8, tree_depth: u8) -> Result<Self, MerkleTreeError> {
+        if sub_depth == 0
+            || sub_depth > tree_depth
+            || usize::from(tree_depth) > MERKLE_TREE_MAX_DEPTH
+        {
+            return Err(MerkleTreeError::InvalidTreeDepth);
+        }
+
+        Ok(AccQueue {
+            sub_depth,
+            tree_depth,
+            number_of_leaves: 0,
+            levels: [[[0; 32]; ARITY]; MERKLE_TREE_MAX_DEPTH],
+            level_lengths: [0; MERKLE_TREE_MAX_DEPTH],
+            subroots: Mapping::new(),
+            number_of_subroots: 0,
+            merged_subroots: 0,
+            filled: false,
+            root: None,
+        })
+    }
+
+    /// Enqueues a leaf, hashing only the groups of siblings it completes, and returns the number of leaves.
+    pub fn enqueue(&mut self, leaf: HashedLeaf) -> Result<u128, MerkleTreeError> {
+        if self.filled {
+            return Err(MerkleTreeError::QueueAlreadyMerging);
+        }
+
+        if self.number_of_leaves >= max_leaves(ARITY, self.tree_depth) {
+            return Err(MerkleTreeError::TreeIsFull);
+        }
+
+        if let Some(subroot) = self.accumulate(0, leaf, self.sub_depth) {
+            self.subroots.insert(self.number_of_subroots, &subroot);
+            self.number_of_subroots += 1;
+        }
+
+        self.number_of_leaves += 1;
+
+        Ok(self.number_of_leaves)
+    }
+
+    /// Merges at most `number_of_subroots` subroots into the main tree and returns the number of subroots left to merge.
+    /// The first call completes the last subtree with zeros, no leaf can be enqueued afterwards.
+    pub fn merge_subroots(&mut self, number_of_subroots: u32) -> u32 {
+        if !self.filled {
+            self.fill();
+        }
+
+        let end = core::cmp::min(
+            self.merged_subroots.saturating_add(number_of_subroots),
+            self.number_of_subroots,
+        );
+
+        for index in self.merged_subroots..end {
+            if let Some(root) = self.accumulate(
+                self.sub_depth,
+                self.subroots.get(index).unwrap_or_default(),
+                self.tree_depth,
+            ) {
+                self.root = Some(root);
+            }
+        }
+
+        self.merged_subroots = end;
+
+        self.number_of_subroots - self.merged_subroots
+    }
+
+    /// Computes the root of the main tree once every subroot has been merged.
+    pub fn merge(&mut self) -> Result<TreeRoot, MerkleTreeError> {
+        if !self.filled {
+            self.fill();
+        }
+
+        if self.merged_subroots != self.number_of_subroots {
+            return Err(MerkleTreeError::SubrootsNotMerged);
+        }
+
+        if self.root.is_none() {
+            let root = self
+                .complete_with_zeros(self.sub_depth, self.tree_depth)
+                .unwrap_or(Self::ZEROS[usize::from(self.tree_depth)]);
+
+            self.root = Some(root);
+        }
+
+        Ok(self.root.unwrap())
+    }
+
+    /// Root of the main tree, once merged.
+    pub fn get_root(&self) -> Option<TreeRoot> {
+        self.root
+    }
+
+    pub fn get_number_of_leaves(&self) -> u128 {
+        self.number_of_leaves
+    }
+
+    pub fn get_number_of_subroots(&self) -> u32 {
+        self.number_of_subroots
+    }
+
+    /// Completes the last subtree with zeros, so that its root can be merged.
+    fn fill(&mut self) {
+        if let Some(subroot) = self.complete_with_zeros(0, self.sub_depth) {
+            self.subroots.insert(self.number_of_subroots, &subroot);
+            self.number_of_subroots += 1;
+        }
+
+        self.filled = true;
+    }
+
+    /// Adds `node` to the group being filled at `level`, hashing the groups it completes up to `top_level`.
+    /// Returns the node reaching `top_level`, if any.
+    fn accumulate(&mut self, level: u8, node: [u8; 32], top_level: u8) -> Option<[u8; 32]> {
+        let mut level = usize::from(level);
+        let mut node = node;
+
+        while level < usize::from(top_level) {
+            let length = usize::from(self.level_lengths[level]);
+            self.levels[level][length] = node;
+
+            if length + 1 < ARITY {
+                self.level_lengths[level] += 1;
+                return None;
+            }
+
+            node = hash_children(&self.levels[level]);
+            self.level_lengths[level] = 0;
+            level += 1;
+        }
+
+        Some(node)
+    }
+
+    /// Completes the groups being filled between `bottom_level` and `top_level` with zeros.
+    /// Returns the node reaching `top_level`, or `None` if no group was being filled.
+    fn complete_with_zeros(&mut self, bottom_level: u8, top_level: u8) -> Option<[u8; 32]> {
+        for level in bottom_level..top_level {
+            let index = usize::from(level);
+            let length = usize::from(self.level_lengths[index]);
+
+            if length == 0 {
+                continue;
+            }
+
+            for sibling in self.levels[index][length..].iter_mut() {
+                *sibling = Self::ZEROS[index];
+            }
+
+            let node = hash_children(&self.levels[index]);
+            self.level_lengths[index] = 0;
+
+            if let Some(top_node) = self.accumulate(level + 1, node, top_level) {
+                return Some(top_node);
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::merkle_tree::MerkleTree;
+    use proptest::prelude::*;
+
+    const TEST_SUB_DEPTH: u8 = 2;
+    const TEST_DEPTH: u8 = 6;
+    const TEST_KEY: u32 = 0x5155_4555;
+
+    fn merge_all<const ARITY: usize, const KEY: u32>(queue: &mut AccQueue<ARITY, KEY>) -> TreeRoot {
+        while queue.merge_subroots(2) > 0 {}
+
+        queue.merge().unwrap()
+    }
+
+    #[ink::test]
+    fn test_error_when_depths_are_invalid() {
+        assert_eq!(
+            AccQueue::<2, TEST_KEY>::new(0, TEST_DEPTH).err(),
+            Some(MerkleTreeError::InvalidTreeDepth)
+        );
+        assert_eq!(
+            AccQueue::<2, TEST_KEY>::new(TEST_DEPTH + 1, TEST_DEPTH).err(),
+            Some(MerkleTreeError::InvalidTreeDepth)
+        );
+        assert_eq!(
+            AccQueue::<5, TEST_KEY>::new(TEST_SUB_DEPTH, (MERKLE_TREE_MAX_DEPTH + 1) as u8).err(),
+            Some(MerkleTreeError::InvalidTreeDepth)
+        );
+    }
+
+    #[ink::test]
+    fn test_empty_queue_root_is_zero_value() {
+        let mut queue = AccQueue::<2, TEST_KEY>::new(TEST_SUB_DEPTH, TEST_DEPTH).unwrap();
+
+        assert_eq!(
+            merge_all(&mut queue),
+            MerkleTree::<2>::new(TEST_DEPTH).unwrap().get_root()
+        );
+    }
+
+    #[ink::test]
+    fn test_enqueue_hashes_only_complete_subtrees() {
+        let mut queue = AccQueue::<5, TEST_KEY>::new(TEST_SUB_DEPTH, TEST_DEPTH).unwrap();
+
+        for i in 0..26u8 {
+            queue.enqueue([i; 32]).unwrap();
+        }
+
+        assert_eq!(queue.get_number_of_leaves(), 26);
+        assert_eq!(queue.get_number_of_subroots(), 1);
+    }
+
+    #[ink::test]
+    fn test_error_when_enqueue_after_merge_started() {
+        let mut queue = AccQueue::<2, TEST_KEY>::new(TEST_SUB_DEPTH, TEST_DEPTH).unwrap();
+
+        queue.enqueue([1; 32]).unwrap();
+        queue.merge_subroots(1);
+
+        assert_eq!(
+            queue.enqueue([2; 32]),
+            Err(MerkleTreeError::QueueAlreadyMerging)
+        );
+    }
+
+    #[ink::test]
+    fn test_error_when_merge_before_subroots_are_merged() {
+        let mut queue = AccQueue::<2, TEST_KEY>::new(TEST_SUB_DEPTH, TEST_DEPTH).unwrap();
+
+        for i in 0..9u8 {
+            queue.enqueue([i; 32]).unwrap();
+        }
+
+        assert_eq!(queue.merge(), Err(MerkleTreeError::SubrootsNotMerged));
+        assert_eq!(queue.merge_subroots(1), 2);
+        assert_eq!(queue.merge(), Err(MerkleTreeError::SubrootsNotMerged));
+        assert_eq!(queue.merge_subroots(5), 0);
+        assert!(queue.merge().is_ok());
+    }
+
+    #[ink::test]
+    fn test_error_when_queue_is_full() {
+        let mut queue = AccQueue::<2, TEST_KEY>::new(1, 3).unwrap();
+
+        for i in 0..8u8 {
+            queue.enqueue([i; 32]).unwrap();
+        }
+
+        assert_eq!(queue.enqueue([8; 32]), Err(MerkleTreeError::TreeIsFull));
+    }
+
+    #[ink::test]
+    fn test_queues_with_distinct_keys_keep_distinct_subroots() {
+        let mut queue = AccQueue::<2, TEST_KEY>::new(1, TEST_DEPTH).unwrap();
+        let mut other_queue = AccQueue::<2, { TEST_KEY + 1 }>::new(1, TEST_DEPTH).unwrap();
+        let mut tree = MerkleTree::<2>::new(TEST_DEPTH).unwrap();
+        let mut other_tree = MerkleTree::<2>::new(TEST_DEPTH).unwrap();
+
+        for i in 0..4u8 {
+            queue.enqueue([i; 32]).unwrap();
+            tree.insert_leaf([i; 32]).unwrap();
+            other_queue.enqueue([i + 10; 32]).unwrap();
+            other_tree.insert_leaf([i + 10; 32]).unwrap();
+        }
+
+        assert_eq!(merge_all(&mut queue), tree.get_root());
+        assert_eq!(merge_all(&mut other_queue), other_tree.get_root());
+    }
+
+    /// Enqueues the leaves then checks the merged root against a `MerkleTree` with the same leaves.
+    fn check_root_matches_merkle_tree<const ARITY: usize>(
+        leaves: Vec<[u8; 32]>,
+        sub_depth: u8,
+        subroots_per_merge: u32,
+    ) -> Result<(), TestCaseError> {
+        let mut roots = None;
+
+        // The subroots are kept in the contract storage, which needs an off-chain environment
+        ink_env::test::run_test::<ink_env::DefaultEnvironment, _>(|_| {
+            let mut queue = AccQueue::<ARITY, TEST_KEY>::new(sub_depth, TEST_DEPTH).unwrap();
+            let mut tree = MerkleTree::<ARITY>::new(TEST_DEPTH).unwrap();
+
+            for leaf in leaves {
+                queue.enqueue(leaf).unwrap();
+                tree.insert_leaf(leaf).unwrap();
+            }
+
+            while queue.merge_subroots(subroots_per_merge) > 0 {}
+
+            roots = Some((queue.merge(), queue.get_root(), tree.get_root()));
+
+            Ok(())
+        })
+        .unwrap();
+
+        let (merged_root, queue_root, tree_root) = roots.unwrap();
+
+        prop_assert_eq!(merged_root, Ok(tree_root));
+        prop_assert_eq!(queue_root, Some(tree_root));
+
+        Ok(())
+    }
+
+    proptest! {
+        // Hashing is slow in debug builds, keep the number of cases low
+        #![proptest_config(ProptestConfig::with_cases(16))]
+
+        #[ink::test]
+        fn test_binary_queue_root_matches_merkle_tree(
+            leaves in prop::collection::vec(any::<[u8; 32]>(), 0..40),
+            sub_depth in 1..=TEST_DEPTH,
+            subroots_per_merge in 1..4u32,
+        ) {
+            check_root_matches_merkle_tree::<2>(leaves, sub_depth, subroots_per_merge)?;
+        }
+
+        #[ink::test]
+        fn test_quinary_queue_root_matches_merkle_tree(
+            leaves in prop::collection::vec(any::<[u8; 32]>(), 0..80),
+            sub_depth in 1..=3u8,
+            subroots_per_merge in 1..4u32,
+        ) {
+            check_root_matches_merkle_tree::<5>(leaves, sub_depth, subroots_per_merge)?;
+        }
+    }
+}
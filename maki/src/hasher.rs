@@ -1,92 +1,161 @@
-pub mod hasher {
-    use crate::maki_objects::{Message, StateLeaf};
-    use crate::maki_types::HashedLeaf;
+use crate::maki_objects::{Message, StateLeaf, MESSAGE_DATA_MAX_LENGTH};
+use crate::maki_types::{HashedLeaf, PublicKey};
 
-    use dusk_bls12_381::BlsScalar;
+use dusk_bls12_381::BlsScalar;
 
-    use ink_prelude::vec::Vec;
-    use maki_shared::functions_utils::bytes_to_scalar;
+use ink_prelude::vec::Vec;
+use maki_shared::functions_utils::{bytes_to_scalar, scalar_to_bytes, u128_to_scalar};
 
-    pub fn hash_state_leaf(state_leaf: &StateLeaf) -> HashedLeaf {
-        let mut plain_leaf_voice_credit: [u8; 32] = [0; 32];
-        plain_leaf_voice_credit[0] = (state_leaf.voice_credit_balance >> 8) as u8;
-        plain_leaf_voice_credit[1] = state_leaf.voice_credit_balance as u8;
-
-        let plain_leaf: [[u8; 32]; 4] = [
-            state_leaf.public_key,
-            plain_leaf_voice_credit,
-            state_leaf.vote_option_tree_root,
-            state_leaf.nounce,
-        ];
-
-        poseidon_hash(&plain_leaf)
-    }
-
-    pub fn hash_left_right(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
-        poseidon_hash(&[*left, *right])
-    }
+#[derive(Debug, PartialEq)]
+pub enum HasherError {
+    MessageTooLong,
+}
 
-    pub fn hash_message(message: &Message) -> HashedLeaf {
-        poseidon_hash(&[message.data])
-    }
+pub fn hash_state_leaf(state_leaf: &StateLeaf) -> HashedLeaf {
+    let plain_leaf_voice_credit = scalar_to_bytes(u128_to_scalar(state_leaf.voice_credit_balance));
 
-    fn poseidon_hash(elements_to_hash: &[[u8; 32]]) -> [u8; 32] {
-        let bls_scalars: Vec<BlsScalar> = elements_to_hash
-            .iter()
-            .map(|i| bytes_to_scalar(*i))
-            .collect();
+    let plain_leaf: [[u8; 32]; 5] = [
+        scalar_to_bytes(state_leaf.public_key.x),
+        scalar_to_bytes(state_leaf.public_key.y),
+        plain_leaf_voice_credit,
+        state_leaf.vote_option_tree_root,
+        state_leaf.nounce,
+    ];
 
-        let result = dusk_poseidon::sponge::hash(&bls_scalars);
+    poseidon_hash(&plain_leaf)
+}
 
-        let hash_bytes = scalar_to_bytes(result);
+/// Hashes the children of a node of a Merkle tree, from left to right. The tree arity is the number of children.
+pub fn hash_children(children: &[[u8; 32]]) -> [u8; 32] {
+    poseidon_hash(children)
+}
 
-        hash_bytes
+/// Hashes the message type, the ciphertext length, every element of the ciphertext and the ephemeral public key
+/// the ciphertext was encrypted with. The ciphertext is padded with zeros to `MESSAGE_DATA_MAX_LENGTH` elements,
+/// so that the message processing circuit hashes every message the same way. A longer ciphertext can not be hashed.
+pub fn hash_message(
+    message: &Message,
+    ecdh_public_key: &PublicKey,
+) -> Result<HashedLeaf, HasherError> {
+    if message.data.len() > MESSAGE_DATA_MAX_LENGTH {
+        return Err(HasherError::MessageTooLong);
     }
 
-    fn scalar_to_bytes(scalar: BlsScalar) -> [u8; 32] {
-        u64_to_bytes(*scalar.internal_repr())
-    }
+    let mut elements: Vec<[u8; 32]> = Vec::with_capacity(MESSAGE_DATA_MAX_LENGTH + 4);
 
-    fn u64_to_bytes(array: [u64; 4]) -> [u8; 32] {
-        let mut result = [0; 32];
+    elements.push(scalar_to_bytes(u128_to_scalar(u128::from(
+        message.message_type,
+    ))));
+    elements.push(scalar_to_bytes(u128_to_scalar(message.data.len() as u128)));
+    elements.extend_from_slice(&message.data);
+    elements.resize(MESSAGE_DATA_MAX_LENGTH + 2, [0; 32]);
+    elements.push(scalar_to_bytes(ecdh_public_key.x));
+    elements.push(scalar_to_bytes(ecdh_public_key.y));
 
-        for i in 0..array.len() {
-            let bytes_array = array[i].to_be_bytes();
-            for j in 0..bytes_array.len() {
-                result[i * 8 + j] = bytes_array[j];
-            }
-        }
+    Ok(poseidon_hash(&elements))
+}
 
-        result
-    }
+fn poseidon_hash(elements_to_hash: &[[u8; 32]]) -> [u8; 32] {
+    let bls_scalars: Vec<BlsScalar> = elements_to_hash
+        .iter()
+        .map(|i| bytes_to_scalar(*i))
+        .collect();
 
-    #[test]
-    fn u64_to_bytes_works_under_256() {
-        let array: [u64; 4] = [10, 255, 0, 3];
+    let result = dusk_poseidon::sponge::hash(&bls_scalars);
 
-        let result = u64_to_bytes(array);
+    scalar_to_bytes(result)
+}
 
-        assert_eq!(
-            [
-                0, 0, 0, 0, 0, 0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 0, 3
-            ],
-            result
-        )
-    }
+/// Hashes the result of each vote option, the voice credits spent by all the users and the salt,
+/// the opening of the tally commitment proved by the tally circuit.
+pub fn hash_tally(results: &[u128], total_spent_voice_credits: u128, salt: &[u8; 32]) -> [u8; 32] {
+    let mut elements: Vec<[u8; 32]> = results
+        .iter()
+        .map(|result| scalar_to_bytes(u128_to_scalar(*result)))
+        .collect();
+    elements.push(scalar_to_bytes(u128_to_scalar(total_spent_voice_credits)));
+    elements.push(*salt);
+
+    poseidon_hash(&elements)
+}
 
-    #[test]
-    fn u64_to_bytes_works_over_256() {
-        let array: [u64; 4] = [196710, 257, 0, 899];
+#[test]
+fn hash_message_commits_to_every_element_and_ecdh_public_key() {
+    let message = Message::new(1, ink_prelude::vec![[2; 32], [3; 32]]);
+    let ecdh_public_key = PublicKey::new(BlsScalar::from(4), BlsScalar::from(5));
+    let hash = hash_message(&message, &ecdh_public_key).unwrap();
+
+    assert_ne!(
+        hash_message(&Message::new(2, message.data.clone()), &ecdh_public_key).unwrap(),
+        hash
+    );
+    assert_ne!(
+        hash_message(
+            &Message::new(1, ink_prelude::vec![[2; 32], [5; 32]]),
+            &ecdh_public_key
+        )
+        .unwrap(),
+        hash
+    );
+    assert_ne!(
+        hash_message(
+            &Message::new(1, ink_prelude::vec![[2; 32]]),
+            &ecdh_public_key
+        )
+        .unwrap(),
+        hash
+    );
+    assert_ne!(
+        hash_message(
+            &Message::new(1, ink_prelude::vec![[2; 32], [3; 32], [0; 32]]),
+            &ecdh_public_key
+        )
+        .unwrap(),
+        hash
+    );
+    assert_ne!(
+        hash_message(
+            &message,
+            &PublicKey::new(BlsScalar::from(5), BlsScalar::from(4))
+        )
+        .unwrap(),
+        hash
+    );
+    assert_eq!(hash_message(&message, &ecdh_public_key), Ok(hash));
+}
 
-        let result = u64_to_bytes(array);
+#[test]
+fn hash_message_longer_than_max_length_returns_error() {
+    let ecdh_public_key = PublicKey::new(BlsScalar::from(4), BlsScalar::from(5));
+
+    assert!(hash_message(
+        &Message::new(1, ink_prelude::vec![[2; 32]; MESSAGE_DATA_MAX_LENGTH]),
+        &ecdh_public_key
+    )
+    .is_ok());
+    assert_eq!(
+        hash_message(
+            &Message::new(1, ink_prelude::vec![[2; 32]; MESSAGE_DATA_MAX_LENGTH + 1]),
+            &ecdh_public_key
+        ),
+        Err(HasherError::MessageTooLong)
+    );
+}
 
-        assert_eq!(
-            [
-                0, 0, 0, 0, 0, 3, 0, 102, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
-                0, 0, 0, 3, 131
-            ],
-            result
-        )
-    }
+#[test]
+fn hash_tally_matches_tally_commitment_of_circuit() {
+    let tally = plonk_prover::Tally {
+        results: ink_prelude::vec![3, 0, 7],
+        total_spent_voice_credits: 58,
+        salt: BlsScalar::from(11),
+    };
+
+    assert_eq!(
+        hash_tally(&tally.results, 58, &scalar_to_bytes(tally.salt)),
+        scalar_to_bytes(tally.commitment())
+    );
+    assert_ne!(
+        hash_tally(&tally.results, 58, &scalar_to_bytes(BlsScalar::from(12))),
+        scalar_to_bytes(tally.commitment())
+    );
 }
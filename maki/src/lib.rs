@@ -1,62 +1,124 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
-mod hasher;
-mod maki_objects;
-mod maki_types;
-mod merkle_tree;
+pub mod acc_queue;
+pub mod hasher;
+pub mod maki_objects;
+pub mod maki_types;
+pub mod merkle_tree;
 mod snark_verifier;
 
 #[ink::contract]
-pub mod maki {
-
-    use maki_shared::functions_utils::generate_public_parameters;
-    use maki_shared::types::SerializedProof;
-
-    use crate::hasher::hasher::{hash_message, hash_state_leaf};
-    use crate::maki_objects::{Message, StateLeaf};
-    use crate::maki_types::PublicKey;
-    use crate::merkle_tree::MerkleTree;
-    use crate::snark_verifier::verify_proof_process_message;
+pub mod poll {
+
+    use ink::prelude::vec::Vec;
+    use ink::storage::Lazy;
+    use maki_shared::functions_utils::is_canonical_scalar;
+    use maki_shared::types::{PoseidonHash, SerializedProof, TreeRoot};
+
+    use crate::acc_queue::AccQueue;
+    use crate::hasher::{hash_message, hash_tally, HasherError};
+    use crate::maki_objects::{
+        Message, PollPhase, MESSAGE_DATA_MAX_LENGTH, MESSAGE_TREE_SUB_DEPTH,
+    };
+    use crate::maki_types::{PollId, PublicKey};
+    use crate::merkle_tree::{MerkleTree, MerkleTreeError};
+    use crate::snark_verifier::{verify_proof_process_message, verify_proof_vote_tally};
+    use plonk_prover::{
+        MESSAGE_BATCH_SIZE, MESSAGE_TREE_DEPTH, NUMBER_OF_VOTE_OPTIONS, STATE_TREE_DEPTH,
+        TALLY_BATCH_SIZE,
+    };
+
+    /// Storage key of the subroots of the message queue.
+    const MESSAGE_QUEUE_KEY: u32 = 0x5355_4252;
 
     #[ink(storage)]
-    pub struct Maki {
-        // Use to determine if a user can still sign-up/vote
+    pub struct Poll {
+        poll_id: PollId,
+
+        // Use to determine when the voting, processing and tallying phases end
         contract_start_timestamp: Timestamp,
-        signup_duration_seconds: u32,
         vote_duration_seconds: u32,
+        processing_duration_seconds: u32,
+        tallying_duration_seconds: u32,
 
+        coordinator: AccountId,
         coordinator_public_key: PublicKey,
 
-        user_vote_credit: u16,
+        // Verifier keys of the circuits, only loaded when a proof is verified
+        process_message_verifier_key: Lazy<Vec<u8>>,
+        vote_tally_verifier_key: Lazy<Vec<u8>>,
+
+        // Bond posted by the coordinator, released to the coordinator once the tally is published or to the beneficiary
+        // if the poll is voided, and then withdrawn by its recipient
+        coordinator_bond: Balance,
+        bond_beneficiary: AccountId,
+        bond_recipient: Option<AccountId>,
 
-        // State
-        message_tree: MerkleTree,
-        state_tree: MerkleTree,
+        // State, the messages are accumulated in subtrees which are merged once the voting period ended
+        message_queue: AccQueue<2, MESSAGE_QUEUE_KEY>,
 
-        // Merkle Root of the state_tree
+        // Root of the registry's state tree when the poll was deployed, advanced by each processed batch of messages
         state_root: [u8; 32],
 
-        tree_depth: u8,
+        // Number of users signed up when the poll was deployed, whose state leaves are tallied
+        number_sign_ups: u32,
+
+        // Merkle Root of the message tree the messages are processed against
+        message_root: [u8; 32],
+
+        message_tree_depth: u8,
 
         number_messages: u32,
+
+        // Processing
+        message_batch_size: u32,
+        processed_messages_count: u32,
+
+        phase: PollPhase,
+
+        // Tally, the state leaves are tallied in batches, each proving a new commitment to the tally
+        tallied_state_leaves_count: u32,
+        tally_commitment: Option<PoseidonHash>,
+        tally_results: Vec<u128>,
     }
 
     /// Errors which may be returned from the smart contract
     #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
     #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
     pub enum Error {
-        SignUpPeriodEnded,
         VotingPeriodEnded,
         MessageLimitReached,
         VotingPeriodNotEnded,
+        MessagesNotProcessed,
+        TallyAlreadyPublished,
+        InvalidProof,
+        TreesNotMerged,
+        InvalidPhase,
+        BatchOutOfOrder,
+        DeadlineNotPassed,
+        BondTransferFailed,
+        InvalidMessageLength,
+        InvalidPublicKey,
+        SubrootsNotMerged,
+        StateLeavesAlreadyTallied,
+        StateLeavesNotTallied,
+        InvalidTallyResults,
+        InvalidTreeDepth,
+        InvalidMessageBatchSize,
+        MissingCoordinatorBond,
+        MessageQueueClosed,
+        InvalidMessageElement,
+        MessageQueueError,
+        NotCoordinator,
+        BondNotReleased,
     }
 
     pub type Result<T> = core::result::Result<T, Error>;
 
-    /// SignedUp event when a user signed up successfully
+    /// PhaseChanged event when the poll moved to a new phase
     #[ink(event)]
-    pub struct SignedUp {
-        user_public_key: PublicKey,
+    pub struct PhaseChanged {
+        phase: PollPhase,
     }
 
     /// MessagePublished event when a user published a message successfully
@@ -66,57 +128,148 @@ pub mod maki {
         ecdh_public_key: PublicKey,
     }
 
-    impl Maki {
+    /// MessageBatchProcessed event when the coordinator proved the processing of a batch of messages
+    #[ink(event)]
+    pub struct MessageBatchProcessed {
+        batch_start_index: u32,
+        batch_end_index: u32,
+        state_root: TreeRoot,
+    }
+
+    /// TallyBatchProcessed event when the coordinator proved the tally of a batch of state leaves
+    #[ink(event)]
+    pub struct TallyBatchProcessed {
+        batch_start_index: u32,
+        tally_commitment: PoseidonHash,
+    }
+
+    /// TallyPublished event when the coordinator published the results of the tally commitment
+    #[ink(event)]
+    pub struct TallyPublished {
+        tally_commitment: PoseidonHash,
+        results: Vec<u128>,
+        total_spent_voice_credits: u128,
+    }
+
+    impl Poll {
+        /// Polls are deployed by the registry, which provides the root of its state tree at deployment time.
+        /// Messages are only accepted once the coordinator posted its bond with `post_bond`. The depths of the trees
+        /// and the message batch size must be the ones the circuits are compiled with, the poll could not be proved
+        /// otherwise.
+        /// ## Arguments
+        ///
+        /// * `processing_duration_seconds` - Time given to the coordinator to process the messages once the voting phase ended
+        ///
+        /// * `tallying_duration_seconds` - Time given to the coordinator to tally the votes once the processing deadline passed
+        ///
+        /// * `coordinator` - Account posting the bond, which is refunded to it once the tally is published
+        ///
+        /// * `bond_beneficiary` - Account the bond is paid to if the poll is voided
+        ///
+        /// * `number_sign_ups` - Number of users signed up to the registry, whose state leaves are tallied
+        ///
+        /// * `state_tree_depth` - Depth of the registry's quinary state tree, must be `STATE_TREE_DEPTH`
+        ///
+        /// * `message_tree_depth` - Depth of the binary message tree, must be `MESSAGE_TREE_DEPTH`
+        ///
+        /// * `message_batch_size` - Number of messages processed by a proof, must be `MESSAGE_BATCH_SIZE`
+        ///
+        /// * `process_message_verifier_key` - Verifier key of the process message circuit, compiled off-chain over the public parameters
+        ///
+        /// * `vote_tally_verifier_key` - Verifier key of the vote tally circuit, compiled off-chain over the public parameters
         #[ink(constructor)]
+        #[allow(clippy::too_many_arguments)]
         pub fn new(
-            signup_duration_seconds: u32,
+            poll_id: PollId,
             vote_duration_seconds: u32,
+            processing_duration_seconds: u32,
+            tallying_duration_seconds: u32,
+            coordinator: AccountId,
             coordinator_public_key: PublicKey,
-            user_vote_credit: u16,
-            tree_depth: u8,
-        ) -> Self {
-            let state_merkle_tree = MerkleTree::new(tree_depth).unwrap();
-            Self {
-                signup_duration_seconds,
+            bond_beneficiary: AccountId,
+            state_root: TreeRoot,
+            number_sign_ups: u32,
+            state_tree_depth: u8,
+            message_tree_depth: u8,
+            message_batch_size: u32,
+            process_message_verifier_key: Vec<u8>,
+            vote_tally_verifier_key: Vec<u8>,
+        ) -> Result<Self> {
+            if usize::from(state_tree_depth) != STATE_TREE_DEPTH
+                || usize::from(message_tree_depth) != MESSAGE_TREE_DEPTH
+            {
+                return Err(Error::InvalidTreeDepth);
+            }
+
+            if message_batch_size as usize != MESSAGE_BATCH_SIZE {
+                return Err(Error::InvalidMessageBatchSize);
+            }
+
+            let message_queue = AccQueue::new(
+                core::cmp::min(MESSAGE_TREE_SUB_DEPTH, message_tree_depth),
+                message_tree_depth,
+            )
+            .map_err(|_| Error::InvalidTreeDepth)?;
+            let message_root = MerkleTree::<2>::new(message_tree_depth)
+                .map_err(|_| Error::InvalidTreeDepth)?
+                .get_root();
+
+            let mut process_message_verifier_key_storage = Lazy::new();
+            process_message_verifier_key_storage.set(&process_message_verifier_key);
+            let mut vote_tally_verifier_key_storage = Lazy::new();
+            vote_tally_verifier_key_storage.set(&vote_tally_verifier_key);
+
+            Ok(Self {
+                poll_id,
                 vote_duration_seconds,
+                processing_duration_seconds,
+                tallying_duration_seconds,
+                coordinator,
                 coordinator_public_key,
-                user_vote_credit,
+                process_message_verifier_key: process_message_verifier_key_storage,
+                vote_tally_verifier_key: vote_tally_verifier_key_storage,
+                coordinator_bond: 0,
+                bond_beneficiary,
+                bond_recipient: None,
                 contract_start_timestamp: Self::env().block_timestamp(),
-                message_tree: MerkleTree::new(tree_depth).unwrap(),
-                state_root: state_merkle_tree.get_root(),
-                state_tree: state_merkle_tree,
+                state_root,
+                number_sign_ups,
+                message_root,
+                message_queue,
                 number_messages: 0,
-                tree_depth,
-            }
+                message_tree_depth,
+                message_batch_size,
+                processed_messages_count: 0,
+                phase: PollPhase::Voting,
+                tallied_state_leaves_count: 0,
+                tally_commitment: None,
+                tally_results: Vec::new(),
+            })
         }
 
-        /// Sign Up can be called by any user whishing to cast a vote.
-        /// ## Arguments
-        ///
-        /// * `user_public_key` - User's public key that will be used by the coordinator to decrypt commands (encrypted using a shared key)
+        /// Post bond can only be called by the coordinator while voting, the transferred value is added to its bond.
+        /// Users can publish messages once the bond is posted.
         ///
         /// ## Returns
-        #[ink(message)]
-        pub fn sign_up(&mut self, user_public_key: PublicKey) -> Result<()> {
-            let block_timestamp = self.env().block_timestamp();
-
-            if self.contract_start_timestamp + u64::from(self.signup_duration_seconds) * 1000
-                < block_timestamp
-            {
-                return Err(Error::SignUpPeriodEnded);
+        #[ink(message, payable)]
+        pub fn post_bond(&mut self) -> Result<()> {
+            if self.env().caller() != self.coordinator {
+                return Err(Error::NotCoordinator);
             }
 
-            let state_leaf =
-                StateLeaf::new(user_public_key, self.user_vote_credit, [0; 32], [0; 32]);
+            self.update_phase();
 
-            let hashed_leaf = hash_state_leaf(&state_leaf);
-
-            let result = self.state_tree.insert_leaf(hashed_leaf);
+            if self.phase != PollPhase::Voting {
+                return Err(Error::VotingPeriodEnded);
+            }
 
-            if result.is_ok() {
-                self.env().emit_event(SignedUp { user_public_key });
+            let bond = self.env().transferred_value();
+            if bond == 0 {
+                return Err(Error::MissingCoordinatorBond);
             }
 
+            self.coordinator_bond += bond;
+
             Ok(())
         }
 
@@ -129,157 +282,549 @@ pub mod maki {
         /// * `message` - User's (encrypted) message containing the command(s)
         ///
         /// ## Returns
+        #[ink(message)]
         pub fn publish_message(
             &mut self,
             message: Message,
             ecdh_public_key: PublicKey,
         ) -> Result<()> {
-            if self.number_messages >= 2u32.pow(self.tree_depth as u32) - 1 {
+            if self.number_messages >= 2u32.pow(self.message_tree_depth as u32) - 1 {
                 return Err(Error::MessageLimitReached);
             }
 
-            let block_timestamp = self.env().block_timestamp();
+            if message.data.is_empty() || message.data.len() > MESSAGE_DATA_MAX_LENGTH {
+                return Err(Error::InvalidMessageLength);
+            }
 
-            if self.contract_start_timestamp
-                + u64::from(self.signup_duration_seconds) * 1000
-                + u64::from(self.vote_duration_seconds) * 1000
-                < block_timestamp
-            {
+            // The circuit could not reproduce the hash of an element out of the scalar field, nor prove its batch
+            if !message.data.iter().copied().all(is_canonical_scalar) {
+                return Err(Error::InvalidMessageElement);
+            }
+
+            if !ecdh_public_key.is_valid() {
+                return Err(Error::InvalidPublicKey);
+            }
+
+            self.update_phase();
+
+            if self.phase != PollPhase::Voting {
                 return Err(Error::VotingPeriodEnded);
             }
 
-            let leaf = hash_message(&message);
+            if self.coordinator_bond == 0 {
+                return Err(Error::MissingCoordinatorBond);
+            }
+
+            let leaf = hash_message(&message, &ecdh_public_key).map_err(|error| match error {
+                HasherError::MessageTooLong => Error::InvalidMessageLength,
+            })?;
+
+            self.message_queue
+                .enqueue(leaf)
+                .map_err(|error| match error {
+                    MerkleTreeError::TreeIsFull => Error::MessageLimitReached,
+                    // The queue is only merged once the voting period ended, which is already rejected above.
+                    // Kept so that a queue closed by other means is not reported as full.
+                    MerkleTreeError::QueueAlreadyMerging => Error::MessageQueueClosed,
+                    // Not returned when enqueuing, reported rather than trapping if it ever changes
+                    MerkleTreeError::InvalidTreeDepth
+                    | MerkleTreeError::LeafNotFound
+                    | MerkleTreeError::InvalidPath
+                    | MerkleTreeError::SubrootsNotMerged => Error::MessageQueueError,
+                })?;
+
+            self.number_messages += 1;
+
+            self.env().emit_event(MessagePublished {
+                message,
+                ecdh_public_key,
+            });
+
+            Ok(())
+        }
+
+        /// Merge message subroots can be called by any user once the voting period ended.
+        /// It merges the roots of the subtrees the messages were accumulated in into the message tree,
+        /// the merge can be spread over several calls if there are many messages.
+        /// ## Arguments
+        ///
+        /// * `number_of_subroots` - Maximum number of subroots to merge
+        ///
+        /// ## Returns
+        ///
+        /// The number of subroots left to merge
+        #[ink(message)]
+        pub fn merge_message_subroots(&mut self, number_of_subroots: u32) -> Result<u32> {
+            self.update_phase();
+
+            match self.phase {
+                PollPhase::MergingTrees => {}
+                PollPhase::Voting => return Err(Error::VotingPeriodNotEnded),
+                _ => return Err(Error::InvalidPhase),
+            }
 
-            let result = self.message_tree.insert_leaf(leaf);
+            Ok(self.message_queue.merge_subroots(number_of_subroots))
+        }
 
-            if result.is_ok() {
-                self.number_messages += 1;
+        /// Merge message tree can be called by any user once every subroot has been merged.
+        /// It snapshots the message tree root the messages will be processed against.
+        ///
+        /// ## Returns
+        #[ink(message)]
+        pub fn merge_message_tree(&mut self) -> Result<()> {
+            self.update_phase();
 
-                self.env().emit_event(MessagePublished {
-                    message,
-                    ecdh_public_key,
-                });
+            match self.phase {
+                PollPhase::MergingTrees => {}
+                PollPhase::Voting => return Err(Error::VotingPeriodNotEnded),
+                _ => return Err(Error::InvalidPhase),
             }
 
+            self.message_root = self
+                .message_queue
+                .merge()
+                .map_err(|_| Error::SubrootsNotMerged)?;
+
+            self.set_phase(PollPhase::Processing);
+
             Ok(())
         }
 
         /// Process messages can be called by any user, however, only the coordinator is supposed to be able to decrypt the message.
         /// Therefore the coordinator is the only user that will be able to provide a verified proof.
+        /// Messages are processed in batches of `message_batch_size` messages, in the order they were published.
         /// ## Arguments
         ///
         /// * `proof` - The zk-SNARK proof
         ///
+        /// * `batch_start_index` - Index of the first message of the batch, must be the number of messages already processed
+        ///
+        /// * `new_state_root` - Root of the state tree once the batch has been processed
+        ///
         /// ## Returns
+        #[ink(message)]
         pub fn process_messages(
             &mut self,
             proof: SerializedProof,
-            public_key: PublicKey,
+            batch_start_index: u32,
+            new_state_root: TreeRoot,
         ) -> Result<()> {
-            let block_timestamp = self.env().block_timestamp();
+            self.update_phase();
 
-            if self.contract_start_timestamp
-                + u64::from(self.signup_duration_seconds) * 1000
-                + u64::from(self.vote_duration_seconds) * 1000
-                >= block_timestamp
-            {
-                return Err(Error::VotingPeriodNotEnded);
+            match self.phase {
+                PollPhase::Processing => {}
+                PollPhase::Voting => return Err(Error::VotingPeriodNotEnded),
+                PollPhase::MergingTrees => return Err(Error::TreesNotMerged),
+                _ => return Err(Error::InvalidPhase),
             }
 
-            // TODO
+            if batch_start_index != self.processed_messages_count {
+                return Err(Error::BatchOutOfOrder);
+            }
 
-            let public_parameters = generate_public_parameters(
-                &self.state_tree.get_root(),
-                &public_key,
-                &self.coordinator_public_key,
+            let batch_end_index = core::cmp::min(
+                batch_start_index + self.message_batch_size,
+                self.number_messages,
             );
 
-            let proved = verify_proof_process_message(&proof, &public_parameters);
+            let verifier_key = self.process_message_verifier_key.get().unwrap_or_default();
+
+            let proved = verify_proof_process_message(
+                &proof,
+                &verifier_key,
+                self.message_root,
+                self.state_root,
+                new_state_root,
+                self.coordinator_public_key,
+                batch_start_index,
+                batch_end_index,
+                self.poll_id,
+            );
 
-            if proved {
-                self.state_root = self.state_tree.get_root();
+            if !proved {
+                return Err(Error::InvalidProof);
+            }
+
+            self.state_root = new_state_root;
+            self.processed_messages_count = batch_end_index;
+
+            self.env().emit_event(MessageBatchProcessed {
+                batch_start_index,
+                batch_end_index,
+                state_root: new_state_root,
+            });
+
+            if self.processed_messages_count == self.number_messages {
+                self.set_phase(PollPhase::Tallying);
             }
 
             Ok(())
         }
-    }
 
-    #[cfg(test)]
-    mod tests {
-        // Imports all the definitions from the outer scope so we can use them here.
-        use super::*;
+        /// Tally votes can be called by any user once all the messages have been processed, however, only the coordinator
+        /// knows the decrypted votes and is therefore the only user that will be able to provide a verified proof of the tally.
+        /// The ballots are tallied in batches of `TALLY_BATCH_SIZE` state leaves, in the order of the state tree.
+        /// ## Arguments
+        ///
+        /// * `proof` - The zk-SNARK proof
+        ///
+        /// * `new_tally_commitment` - Commitment to the tally once the batch has been tallied
+        ///
+        /// ## Returns
+        #[ink(message)]
+        pub fn tally_votes(
+            &mut self,
+            proof: SerializedProof,
+            new_tally_commitment: PoseidonHash,
+        ) -> Result<()> {
+            self.update_phase();
 
-        use ink_env;
+            match self.phase {
+                PollPhase::Tallying => {}
+                PollPhase::Finalized => return Err(Error::TallyAlreadyPublished),
+                PollPhase::Voided => return Err(Error::InvalidPhase),
+                _ => return Err(Error::MessagesNotProcessed),
+            }
 
-        use crate::merkle_tree::MERKLE_TREE_DEFAULT_DEPTH;
+            if self.is_tally_computed() {
+                return Err(Error::StateLeavesAlreadyTallied);
+            }
 
-        type Event = <Maki as ::ink::reflect::ContractEventBase>::Type;
+            let batch_start_index = self.tallied_state_leaves_count;
+            // The tally is empty before the first batch
+            let current_tally_commitment = self.tally_commitment.unwrap_or([0; 32]);
 
-        #[ink::test]
-        fn sign_up_emits_sign_up_event() {
-            let mut maki = Maki::new(10000, 10000, [0; 32], 100, MERKLE_TREE_DEFAULT_DEPTH as u8);
+            let verifier_key = self.vote_tally_verifier_key.get().unwrap_or_default();
 
-            let upk = [1; 32];
+            let proved = verify_proof_vote_tally(
+                &proof,
+                &verifier_key,
+                self.state_root,
+                batch_start_index,
+                current_tally_commitment,
+                new_tally_commitment,
+            );
 
-            let result = maki.sign_up(upk);
+            if !proved {
+                return Err(Error::InvalidProof);
+            }
 
-            assert!(result.is_ok());
+            self.tally_commitment = Some(new_tally_commitment);
+            self.tallied_state_leaves_count = batch_start_index + TALLY_BATCH_SIZE as u32;
 
-            let events = ink_env::test::recorded_events().collect::<Vec<_>>();
+            self.env().emit_event(TallyBatchProcessed {
+                batch_start_index,
+                tally_commitment: new_tally_commitment,
+            });
 
-            let events_length = &events.len();
+            Ok(())
+        }
 
-            assert_eq!(*events_length, 1);
-            let sign_up_event = &events[0];
-            let decoded_event = <Event as scale::Decode>::decode(&mut &sign_up_event.data[..])
-                .expect("encountered invalid contract event data buffer");
-            if let Event::SignedUp(SignedUp { user_public_key }) = decoded_event {
-                assert_eq!(
-                    user_public_key, upk,
-                    "encountered invalid SignedUp.user_public_key"
-                );
-            } else {
-                panic!("encountered unexpected event kind: expected a SignedUp event")
+        /// Publish tally results can be called by any user once every state leaf has been tallied, however, only the coordinator
+        /// knows the salt of the tally commitment and is therefore the only user that will be able to open it.
+        /// ## Arguments
+        ///
+        /// * `results` - Number of votes received by each vote option
+        ///
+        /// * `total_spent_voice_credits` - Voice credits spent by all the users
+        ///
+        /// * `salt` - Salt of the tally commitment
+        ///
+        /// ## Returns
+        #[ink(message)]
+        pub fn publish_tally_results(
+            &mut self,
+            results: Vec<u128>,
+            total_spent_voice_credits: u128,
+            salt: [u8; 32],
+        ) -> Result<()> {
+            self.update_phase();
+
+            match self.phase {
+                PollPhase::Tallying => {}
+                PollPhase::Finalized => return Err(Error::TallyAlreadyPublished),
+                PollPhase::Voided => return Err(Error::InvalidPhase),
+                _ => return Err(Error::MessagesNotProcessed),
+            }
+
+            let tally_commitment = match self.tally_commitment {
+                Some(tally_commitment) if self.is_tally_computed() => tally_commitment,
+                _ => return Err(Error::StateLeavesNotTallied),
+            };
+
+            if results.len() != NUMBER_OF_VOTE_OPTIONS
+                || hash_tally(&results, total_spent_voice_credits, &salt) != tally_commitment
+            {
+                return Err(Error::InvalidTallyResults);
             }
+
+            self.tally_results = results.clone();
+
+            self.env().emit_event(TallyPublished {
+                tally_commitment,
+                results,
+                total_spent_voice_credits,
+            });
+
+            self.set_phase(PollPhase::Finalized);
+
+            self.bond_recipient = Some(self.coordinator);
+
+            Ok(())
         }
 
-        #[ink::test]
-        fn sign_up_after_end_of_sign_up_period_returns_error() {
-            let signup_duration: u32 = 60;
-            let vote_duration: u32 = 10000;
-            let mut maki = Maki::new(
-                signup_duration,
-                vote_duration,
+        /// Void poll can be called by any user once the coordinator missed the processing or the tallying deadline,
+        /// the poll being then in the `Voided` phase. The coordinator's bond is released to the beneficiary.
+        ///
+        /// ## Returns
+        #[ink(message)]
+        pub fn void_poll(&mut self) -> Result<()> {
+            self.update_phase();
+
+            match self.phase {
+                PollPhase::Voided => {}
+                PollPhase::Voting => return Err(Error::VotingPeriodNotEnded),
+                PollPhase::MergingTrees | PollPhase::Processing | PollPhase::Tallying => {
+                    return Err(Error::DeadlineNotPassed)
+                }
+                PollPhase::Finalized => return Err(Error::InvalidPhase),
+            }
+
+            // The poll has already been voided
+            if self.bond_recipient.is_some() {
+                return Err(Error::InvalidPhase);
+            }
+
+            self.bond_recipient = Some(self.bond_beneficiary);
+
+            Ok(())
+        }
+
+        /// Withdraw bond can be called by any user once the poll is finalized or voided, it transfers the
+        /// coordinator's bond to the account it was released to. A failed transfer leaves the bond to withdraw,
+        /// nothing is left to transfer once it succeeded.
+        ///
+        /// ## Returns
+        #[ink(message)]
+        pub fn withdraw_bond(&mut self) -> Result<()> {
+            let recipient = self.bond_recipient.ok_or(Error::BondNotReleased)?;
+
+            self.env()
+                .transfer(recipient, self.coordinator_bond)
+                .map_err(|_| Error::BondTransferFailed)?;
+
+            self.coordinator_bond = 0;
+
+            Ok(())
+        }
+
+        /// Returns the identifier the registry gave to the poll.
+        #[ink(message)]
+        pub fn get_poll_id(&self) -> PollId {
+            self.poll_id
+        }
+
+        /// Returns the number of messages whose processing has been proved by the coordinator.
+        #[ink(message)]
+        pub fn get_processed_messages_count(&self) -> u32 {
+            self.processed_messages_count
+        }
+
+        /// Returns the state root, the registry's one at deployment until batches of messages are processed.
+        #[ink(message)]
+        pub fn get_state_root(&self) -> TreeRoot {
+            self.state_root
+        }
+
+        /// Returns the number of users signed up to the registry when the poll was deployed.
+        #[ink(message)]
+        pub fn get_number_sign_ups(&self) -> u32 {
+            self.number_sign_ups
+        }
+
+        /// Returns the phase the poll is currently in, including the phases reached through deadlines since the last
+        /// call storing the phase. The voting phase ends at the voting deadline, and the poll is voided once the
+        /// coordinator missed the processing or the tallying deadline. The other phases only follow the calls moving
+        /// the poll on.
+        #[ink(message)]
+        pub fn get_phase(&self) -> PollPhase {
+            let block_timestamp = self.env().block_timestamp();
+
+            let mut phase = self.phase;
+            while let Some(next_phase) = self.next_timed_phase(phase, block_timestamp) {
+                phase = next_phase;
+            }
+
+            phase
+        }
+
+        /// Returns the bond posted by the coordinator, until it is withdrawn by the account it was released to.
+        #[ink(message)]
+        pub fn get_coordinator_bond(&self) -> Balance {
+            self.coordinator_bond
+        }
+
+        /// Returns the number of votes received by each vote option, once the tally has been published.
+        #[ink(message)]
+        pub fn get_tally_results(&self) -> Option<Vec<u128>> {
+            (self.phase == PollPhase::Finalized).then(|| self.tally_results.clone())
+        }
+
+        fn voting_deadline(&self) -> Timestamp {
+            self.contract_start_timestamp + u64::from(self.vote_duration_seconds) * 1000
+        }
+
+        fn processing_deadline(&self) -> Timestamp {
+            self.voting_deadline() + u64::from(self.processing_duration_seconds) * 1000
+        }
+
+        fn tallying_deadline(&self) -> Timestamp {
+            self.processing_deadline() + u64::from(self.tallying_duration_seconds) * 1000
+        }
+
+        /// Whether every state leaf has been tallied, a tally being proved even without sign-ups.
+        fn is_tally_computed(&self) -> bool {
+            self.tally_commitment.is_some()
+                && self.tallied_state_leaves_count >= self.number_sign_ups
+        }
+
+        /// Phase following `phase` once its deadline is over, if any.
+        fn next_timed_phase(&self, phase: PollPhase, timestamp: Timestamp) -> Option<PollPhase> {
+            match phase {
+                PollPhase::Voting if timestamp > self.voting_deadline() => {
+                    Some(PollPhase::MergingTrees)
+                }
+                PollPhase::MergingTrees | PollPhase::Processing
+                    if timestamp > self.processing_deadline() =>
+                {
+                    Some(PollPhase::Voided)
+                }
+                PollPhase::Tallying if timestamp > self.tallying_deadline() => {
+                    Some(PollPhase::Voided)
+                }
+                _ => None,
+            }
+        }
+
+        /// Stores the phases whose deadlines are over, emitting an event for each transition.
+        fn update_phase(&mut self) {
+            let block_timestamp = self.env().block_timestamp();
+
+            while let Some(next_phase) = self.next_timed_phase(self.phase, block_timestamp) {
+                self.set_phase(next_phase);
+            }
+        }
+
+        fn set_phase(&mut self, phase: PollPhase) {
+            self.phase = phase;
+
+            self.env().emit_event(PhaseChanged { phase });
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        // Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+
+        use ink_env;
+
+        use dusk_bls12_381::BlsScalar;
+        use dusk_jubjub::{JubJubAffine, JubJubScalar, GENERATOR_EXTENDED};
+        use dusk_plonk::prelude::PublicParameters;
+        use dusk_poseidon::sponge;
+        use maki_shared::functions_utils::{scalar_to_bytes, u128_to_scalar};
+        use plonk_prover::{
+            prove_vote_tally, vote_tally_verifier_key, StateLeafOpening, TalliedStateLeaf, Tally,
+            STATE_TREE_DEPTH, TALLY_BATCH_DEPTH,
+        };
+        use rand_core::OsRng;
+
+        type Event = <Poll as ::ink::reflect::ContractEventBase>::Type;
+
+        const TEST_POLL_ID: PollId = 0;
+        const TEST_VOTE_DURATION: u32 = 60;
+        const TEST_MESSAGE_BATCH_SIZE: u32 = MESSAGE_BATCH_SIZE as u32;
+        const TEST_PROCESSING_DURATION: u32 = 60;
+        const TEST_TALLYING_DURATION: u32 = 60;
+        const TEST_COORDINATOR_BOND: Balance = 1000;
+        const TEST_NUMBER_SIGN_UPS: u32 = 7;
+
+        /// Valid public key whose private key is `seed`.
+        fn test_public_key(seed: u64) -> PublicKey {
+            PublicKey::from(JubJubAffine::from(
+                GENERATOR_EXTENDED * JubJubScalar::from(seed),
+            ))
+        }
+
+        /// Deploys a poll whose coordinator is bob and bond beneficiary is django, with the given circuit parameters.
+        fn new_test_poll_with_parameters(
+            state_tree_depth: u8,
+            message_tree_depth: u8,
+            message_batch_size: u32,
+        ) -> Result<Poll> {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+
+            Poll::new(
+                TEST_POLL_ID,
+                TEST_VOTE_DURATION,
+                TEST_PROCESSING_DURATION,
+                TEST_TALLYING_DURATION,
+                accounts.bob,
+                test_public_key(2),
+                accounts.django,
                 [0; 32],
-                100,
-                MERKLE_TREE_DEFAULT_DEPTH as u8,
-            );
+                TEST_NUMBER_SIGN_UPS,
+                state_tree_depth,
+                message_tree_depth,
+                message_batch_size,
+                Vec::new(),
+                Vec::new(),
+            )
+        }
 
-            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(u64::from(
-                signup_duration * 1000 + 1,
-            ));
+        /// Deploys a poll whose coordinator is bob and bond beneficiary is django, without the coordinator's bond.
+        fn new_unbonded_test_poll(vote_duration_seconds: u32) -> Poll {
+            let mut poll = new_test_poll_with_parameters(
+                STATE_TREE_DEPTH as u8,
+                MESSAGE_TREE_DEPTH as u8,
+                TEST_MESSAGE_BATCH_SIZE,
+            )
+            .unwrap();
+            poll.vote_duration_seconds = vote_duration_seconds;
+
+            poll
+        }
 
-            let upk = [1; 32];
+        /// Deploys a poll whose coordinator is bob and bond beneficiary is django, bob then posts the bond.
+        fn new_test_poll(vote_duration_seconds: u32) -> Poll {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+            let mut poll = new_unbonded_test_poll(vote_duration_seconds);
 
-            let result = maki.sign_up(upk);
+            ink_env::test::set_caller::<ink_env::DefaultEnvironment>(accounts.bob);
+            ink_env::test::set_value_transferred::<ink_env::DefaultEnvironment>(
+                TEST_COORDINATOR_BOND,
+            );
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(
+                ink_env::test::callee::<ink_env::DefaultEnvironment>(),
+                TEST_COORDINATOR_BOND,
+            );
+            poll.post_bond().unwrap();
 
-            assert!(result.is_err());
-            assert_eq!(result, Err(Error::SignUpPeriodEnded));
+            poll
         }
 
         #[ink::test]
         fn publish_message_returns_error_on_number_of_message_limit_reached() {
-            let test_tree_depth: u8 = 8;
-
-            let mut maki = Maki::new(60, 10000, [0; 32], 100, test_tree_depth);
+            let mut poll = new_test_poll(10000);
 
-            let msg = Message::new([2; 32]);
-            let upk = [1; 32];
-            for _ in 0..2usize.pow(test_tree_depth as u32) - 1 {
-                maki.publish_message(msg, upk).unwrap();
+            let msg = Message::new(1, vec![[2; 32], [3; 32]]);
+            let upk = test_public_key(1);
+            for _ in 0..2usize.pow(MESSAGE_TREE_DEPTH as u32) - 1 {
+                poll.publish_message(msg.clone(), upk).unwrap();
             }
 
-            let err = maki.publish_message(msg, upk);
+            let err = poll.publish_message(msg, upk);
 
             assert!(err.is_err());
             assert_eq!(err, Err(Error::MessageLimitReached));
@@ -287,11 +832,11 @@ pub mod maki {
 
         #[ink::test]
         fn publish_message_emits_publish_message_event() {
-            let mut maki = Maki::new(10000, 10000, [0; 32], 100, MERKLE_TREE_DEFAULT_DEPTH as u8);
+            let mut poll = new_test_poll(10000);
 
-            let msg = Message::new([2; 32]);
-            let upk = [1; 32];
-            let result = maki.publish_message(msg, upk);
+            let msg = Message::new(1, vec![[2; 32], [3; 32]]);
+            let upk = test_public_key(1);
+            let result = poll.publish_message(msg.clone(), upk);
 
             assert!(result.is_ok());
 
@@ -321,39 +866,750 @@ pub mod maki {
 
         #[ink::test]
         fn publish_message_after_end_of_voting_period_returns_error() {
-            let signup_duration: u32 = 60;
             let vote_duration: u32 = 60;
-            let mut maki = Maki::new(
-                signup_duration,
-                vote_duration,
-                [0; 32],
-                100,
-                MERKLE_TREE_DEFAULT_DEPTH as u8,
-            );
+            let mut poll = new_test_poll(vote_duration);
 
             ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(u64::from(
-                (signup_duration + vote_duration) * 1000 + 1,
+                vote_duration * 1000 + 1,
             ));
 
-            let msg = Message::new([2; 32]);
-            let upk = [1; 32];
+            let msg = Message::new(1, vec![[2; 32], [3; 32]]);
+            let upk = test_public_key(1);
 
-            let result = maki.publish_message(msg, upk);
+            let result = poll.publish_message(msg, upk);
 
             assert!(result.is_err());
             assert_eq!(result, Err(Error::VotingPeriodEnded));
         }
 
+        #[ink::test]
+        fn publish_message_with_invalid_data_length_returns_error() {
+            let mut poll = new_test_poll(10000);
+
+            let upk = test_public_key(1);
+
+            let result = poll.publish_message(Message::new(1, vec![]), upk);
+
+            assert_eq!(result, Err(Error::InvalidMessageLength));
+
+            let result = poll.publish_message(
+                Message::new(1, vec![[2; 32]; MESSAGE_DATA_MAX_LENGTH + 1]),
+                upk,
+            );
+
+            assert_eq!(result, Err(Error::InvalidMessageLength));
+
+            let result =
+                poll.publish_message(Message::new(1, vec![[2; 32]; MESSAGE_DATA_MAX_LENGTH]), upk);
+
+            assert!(result.is_ok());
+        }
+
+        #[ink::test]
+        fn publish_message_once_message_queue_merging_returns_error() {
+            let mut poll = new_test_poll(10000);
+
+            let msg = Message::new(1, vec![[2; 32], [3; 32]]);
+            let upk = test_public_key(1);
+            poll.publish_message(msg.clone(), upk).unwrap();
+            poll.message_queue.merge_subroots(1);
+
+            let result = poll.publish_message(msg, upk);
+
+            assert_eq!(result, Err(Error::MessageQueueClosed));
+            assert_eq!(poll.number_messages, 1);
+            assert_eq!(ink_env::test::recorded_events().count(), 1);
+        }
+
+        #[ink::test]
+        fn publish_message_with_element_out_of_scalar_field_returns_error() {
+            let mut poll = new_test_poll(10000);
+
+            let upk = test_public_key(1);
+
+            let result = poll.publish_message(Message::new(1, vec![[2; 32], [0xff; 32]]), upk);
+
+            assert_eq!(result, Err(Error::InvalidMessageElement));
+            assert_eq!(poll.number_messages, 0);
+            assert_eq!(ink_env::test::recorded_events().count(), 0);
+        }
+
+        #[ink::test]
+        fn publish_message_with_invalid_ecdh_public_key_returns_error() {
+            let mut poll = new_test_poll(10000);
+
+            let msg = Message::new(1, vec![[2; 32], [3; 32]]);
+
+            let result =
+                poll.publish_message(msg.clone(), PublicKey::from(JubJubAffine::identity()));
+
+            assert!(result.is_err());
+            assert_eq!(result, Err(Error::InvalidPublicKey));
+
+            // A key which is not a point of the curve is still decoded from the call's input
+            let mut off_curve_key = test_public_key(1);
+            off_curve_key.x += BlsScalar::one();
+            let decoded_key = <PublicKey as scale::Decode>::decode(
+                &mut &scale::Encode::encode(&off_curve_key)[..],
+            )
+            .unwrap();
+
+            assert_eq!(
+                poll.publish_message(msg, decoded_key),
+                Err(Error::InvalidPublicKey)
+            );
+
+            let events = ink_env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(events.len(), 0);
+        }
+
         #[ink::test]
         fn process_messages_before_voting_period_ends_returns_error() {
-            let mut maki = Maki::new(60, 60, [0; 32], 100, MERKLE_TREE_DEFAULT_DEPTH as u8);
+            let mut poll = new_test_poll(60);
 
             let proof = [123; 1040];
-            let pk: PublicKey = [2;32];
-            let result = maki.process_messages(proof, pk);
+            let new_state_root: TreeRoot = [2; 32];
+            let result = poll.process_messages(proof, 0, new_state_root);
 
             assert!(result.is_err());
             assert_eq!(result, Err(Error::VotingPeriodNotEnded));
         }
+
+        #[ink::test]
+        fn tally_votes_before_messages_processed_returns_error() {
+            let vote_duration: u32 = 60;
+            let mut poll = new_test_poll(vote_duration);
+
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(u64::from(
+                vote_duration * 1000 + 1,
+            ));
+
+            let proof = [123; 1040];
+            let result = poll.tally_votes(proof, [3; 32]);
+
+            assert!(result.is_err());
+            assert_eq!(result, Err(Error::MessagesNotProcessed));
+            assert_eq!(poll.get_tally_results(), None);
+        }
+
+        #[ink::test]
+        fn tally_votes_with_invalid_proof_returns_error() {
+            let mut poll = new_test_poll(60);
+            poll.phase = PollPhase::Tallying;
+
+            let proof = [123; 1040];
+            let result = poll.tally_votes(proof, [3; 32]);
+
+            assert!(result.is_err());
+            assert_eq!(result, Err(Error::InvalidProof));
+            assert_eq!(poll.get_tally_results(), None);
+
+            let events = ink_env::test::recorded_events().collect::<Vec<_>>();
+            assert_eq!(events.len(), 0);
+        }
+
+        #[ink::test]
+        fn tally_votes_after_state_leaves_tallied_returns_error() {
+            let mut poll = new_test_poll(60);
+            poll.phase = PollPhase::Tallying;
+            poll.tally_commitment = Some([3; 32]);
+            poll.tallied_state_leaves_count = TALLY_BATCH_SIZE as u32 * 2;
+
+            let proof = [123; 1040];
+            let result = poll.tally_votes(proof, [4; 32]);
+
+            assert_eq!(result, Err(Error::StateLeavesAlreadyTallied));
+            assert_eq!(poll.tally_commitment, Some([3; 32]));
+        }
+
+        #[ink::test]
+        fn publish_tally_results_before_state_leaves_tallied_returns_error() {
+            let mut poll = new_test_poll(60);
+            poll.phase = PollPhase::Tallying;
+
+            let results = vec![0; NUMBER_OF_VOTE_OPTIONS];
+
+            assert_eq!(
+                poll.publish_tally_results(results.clone(), 0, [1; 32]),
+                Err(Error::StateLeavesNotTallied)
+            );
+
+            poll.tally_commitment = Some(hash_tally(&results, 0, &[1; 32]));
+            poll.tallied_state_leaves_count = TALLY_BATCH_SIZE as u32;
+
+            assert_eq!(
+                poll.publish_tally_results(results, 0, [1; 32]),
+                Err(Error::StateLeavesNotTallied)
+            );
+            assert_eq!(poll.get_tally_results(), None);
+        }
+
+        #[ink::test]
+        fn publish_tally_results_opening_tally_commitment_finalizes_poll() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+            let mut poll = new_test_poll(60);
+            poll.phase = PollPhase::Tallying;
+
+            let mut results = vec![0; NUMBER_OF_VOTE_OPTIONS];
+            results[1] = 4;
+            results[3] = 2;
+            poll.tally_commitment = Some(hash_tally(&results, 20, &[1; 32]));
+            poll.tallied_state_leaves_count = TALLY_BATCH_SIZE as u32 * 2;
+
+            assert_eq!(
+                poll.publish_tally_results(results.clone(), 20, [2; 32]),
+                Err(Error::InvalidTallyResults)
+            );
+            assert_eq!(
+                poll.publish_tally_results(results[1..].to_vec(), 20, [1; 32]),
+                Err(Error::InvalidTallyResults)
+            );
+
+            let coordinator_balance =
+                ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(accounts.bob)
+                    .unwrap();
+
+            assert_eq!(
+                poll.publish_tally_results(results.clone(), 20, [1; 32]),
+                Ok(())
+            );
+            assert_eq!(poll.get_phase(), PollPhase::Finalized);
+            assert_eq!(poll.get_tally_results(), Some(results.clone()));
+            assert_eq!(poll.withdraw_bond(), Ok(()));
+            assert_eq!(
+                ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(accounts.bob)
+                    .unwrap(),
+                coordinator_balance + TEST_COORDINATOR_BOND
+            );
+
+            assert_eq!(
+                poll.publish_tally_results(results, 20, [1; 32]),
+                Err(Error::TallyAlreadyPublished)
+            );
+        }
+
+        #[ink::test]
+        fn publish_tally_results_with_unpayable_bond_finalizes_poll() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+            let mut poll = new_test_poll(60);
+            poll.phase = PollPhase::Tallying;
+
+            let results = vec![0; NUMBER_OF_VOTE_OPTIONS];
+            poll.tally_commitment = Some(hash_tally(&results, 0, &[1; 32]));
+            poll.tallied_state_leaves_count = TEST_NUMBER_SIGN_UPS;
+
+            // The poll can not pay the bond, which is only transferred when it is withdrawn
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(
+                ink_env::test::callee::<ink_env::DefaultEnvironment>(),
+                0,
+            );
+
+            assert_eq!(poll.publish_tally_results(results, 0, [1; 32]), Ok(()));
+            assert_eq!(poll.get_phase(), PollPhase::Finalized);
+            assert_eq!(poll.get_coordinator_bond(), TEST_COORDINATOR_BOND);
+
+            ink_env::test::set_account_balance::<ink_env::DefaultEnvironment>(
+                ink_env::test::callee::<ink_env::DefaultEnvironment>(),
+                TEST_COORDINATOR_BOND,
+            );
+            let coordinator_balance =
+                ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(accounts.bob)
+                    .unwrap();
+
+            assert_eq!(poll.withdraw_bond(), Ok(()));
+            assert_eq!(
+                ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(accounts.bob)
+                    .unwrap(),
+                coordinator_balance + TEST_COORDINATOR_BOND
+            );
+        }
+
+        #[ink::test]
+        fn get_phase_follows_voting_deadline() {
+            let vote_duration: u32 = 60;
+            let poll = new_test_poll(vote_duration);
+
+            assert_eq!(poll.get_phase(), PollPhase::Voting);
+
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(u64::from(
+                vote_duration * 1000 + 1,
+            ));
+
+            assert_eq!(poll.get_phase(), PollPhase::MergingTrees);
+        }
+
+        #[ink::test]
+        fn get_phase_after_missed_deadline_returns_voided() {
+            let vote_duration: u32 = 60;
+            let mut poll = new_test_poll(vote_duration);
+
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(u64::from(
+                (vote_duration + TEST_PROCESSING_DURATION) * 1000 + 1,
+            ));
+
+            assert_eq!(poll.get_phase(), PollPhase::Voided);
+
+            poll.phase = PollPhase::Tallying;
+
+            assert_eq!(poll.get_phase(), PollPhase::Tallying);
+
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(u64::from(
+                (vote_duration + TEST_PROCESSING_DURATION + TEST_TALLYING_DURATION) * 1000 + 1,
+            ));
+
+            assert_eq!(poll.get_phase(), PollPhase::Voided);
+        }
+
+        #[ink::test]
+        fn new_poll_keeps_poll_id_and_state_root() {
+            let state_root: TreeRoot = [7; 32];
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+
+            let poll = Poll::new(
+                42,
+                60,
+                TEST_PROCESSING_DURATION,
+                TEST_TALLYING_DURATION,
+                accounts.bob,
+                test_public_key(2),
+                accounts.django,
+                state_root,
+                TEST_NUMBER_SIGN_UPS,
+                STATE_TREE_DEPTH as u8,
+                MESSAGE_TREE_DEPTH as u8,
+                TEST_MESSAGE_BATCH_SIZE,
+                Vec::new(),
+                Vec::new(),
+            )
+            .unwrap();
+
+            assert_eq!(poll.get_poll_id(), 42);
+            assert_eq!(poll.state_root, state_root);
+            assert_eq!(poll.get_coordinator_bond(), 0);
+        }
+
+        #[ink::test]
+        fn post_bond_by_other_than_coordinator_returns_error() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+            let mut poll = new_unbonded_test_poll(60);
+
+            ink_env::test::set_caller::<ink_env::DefaultEnvironment>(accounts.alice);
+            ink_env::test::set_value_transferred::<ink_env::DefaultEnvironment>(
+                TEST_COORDINATOR_BOND,
+            );
+
+            assert_eq!(poll.post_bond(), Err(Error::NotCoordinator));
+            assert_eq!(poll.get_coordinator_bond(), 0);
+        }
+
+        #[ink::test]
+        fn post_bond_without_transferred_value_returns_error() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+            let mut poll = new_unbonded_test_poll(60);
+
+            ink_env::test::set_caller::<ink_env::DefaultEnvironment>(accounts.bob);
+
+            assert_eq!(poll.post_bond(), Err(Error::MissingCoordinatorBond));
+        }
+
+        #[ink::test]
+        fn post_bond_after_end_of_voting_period_returns_error() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+            let vote_duration: u32 = 60;
+            let mut poll = new_unbonded_test_poll(vote_duration);
+
+            ink_env::test::set_caller::<ink_env::DefaultEnvironment>(accounts.bob);
+            ink_env::test::set_value_transferred::<ink_env::DefaultEnvironment>(
+                TEST_COORDINATOR_BOND,
+            );
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(u64::from(
+                vote_duration * 1000 + 1,
+            ));
+
+            assert_eq!(poll.post_bond(), Err(Error::VotingPeriodEnded));
+        }
+
+        #[ink::test]
+        fn publish_message_before_bond_posted_returns_error() {
+            let mut poll = new_unbonded_test_poll(60);
+
+            let result = poll.publish_message(Message::new(1, vec![[2; 32]]), test_public_key(1));
+
+            assert_eq!(result, Err(Error::MissingCoordinatorBond));
+        }
+
+        #[ink::test]
+        fn new_poll_with_parameters_other_than_circuits_returns_error() {
+            let state_tree_depth = STATE_TREE_DEPTH as u8;
+            let message_tree_depth = MESSAGE_TREE_DEPTH as u8;
+
+            assert_eq!(
+                new_test_poll_with_parameters(
+                    state_tree_depth + 1,
+                    message_tree_depth,
+                    TEST_MESSAGE_BATCH_SIZE
+                )
+                .err(),
+                Some(Error::InvalidTreeDepth)
+            );
+            assert_eq!(
+                new_test_poll_with_parameters(
+                    state_tree_depth,
+                    message_tree_depth - 1,
+                    TEST_MESSAGE_BATCH_SIZE
+                )
+                .err(),
+                Some(Error::InvalidTreeDepth)
+            );
+            assert_eq!(
+                new_test_poll_with_parameters(
+                    state_tree_depth,
+                    message_tree_depth,
+                    TEST_MESSAGE_BATCH_SIZE + 1
+                )
+                .err(),
+                Some(Error::InvalidMessageBatchSize)
+            );
+            assert!(new_test_poll_with_parameters(
+                state_tree_depth,
+                message_tree_depth,
+                TEST_MESSAGE_BATCH_SIZE
+            )
+            .is_ok());
+        }
+
+        #[ink::test]
+        fn void_poll_before_voting_period_ends_returns_error() {
+            let mut poll = new_test_poll(60);
+
+            let result = poll.void_poll();
+
+            assert!(result.is_err());
+            assert_eq!(result, Err(Error::VotingPeriodNotEnded));
+        }
+
+        #[ink::test]
+        fn void_poll_before_processing_deadline_returns_error() {
+            let vote_duration: u32 = 60;
+            let mut poll = new_test_poll(vote_duration);
+
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(u64::from(
+                (vote_duration + TEST_PROCESSING_DURATION) * 1000,
+            ));
+
+            let result = poll.void_poll();
+
+            assert!(result.is_err());
+            assert_eq!(result, Err(Error::DeadlineNotPassed));
+            assert_eq!(poll.get_coordinator_bond(), TEST_COORDINATOR_BOND);
+        }
+
+        #[ink::test]
+        fn void_poll_after_processing_deadline_releases_bond_to_beneficiary() {
+            let accounts = ink_env::test::default_accounts::<ink_env::DefaultEnvironment>();
+            let vote_duration: u32 = 60;
+            let mut poll = new_test_poll(vote_duration);
+
+            let beneficiary_balance =
+                ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(accounts.django)
+                    .unwrap();
+
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(u64::from(
+                (vote_duration + TEST_PROCESSING_DURATION) * 1000 + 1,
+            ));
+
+            assert_eq!(poll.withdraw_bond(), Err(Error::BondNotReleased));
+
+            let result = poll.void_poll();
+
+            assert!(result.is_ok());
+            assert_eq!(poll.get_phase(), PollPhase::Voided);
+            assert_eq!(poll.get_coordinator_bond(), TEST_COORDINATOR_BOND);
+
+            assert_eq!(poll.withdraw_bond(), Ok(()));
+            assert_eq!(poll.get_coordinator_bond(), 0);
+            assert_eq!(
+                ink_env::test::get_account_balance::<ink_env::DefaultEnvironment>(accounts.django)
+                    .unwrap(),
+                beneficiary_balance + TEST_COORDINATOR_BOND
+            );
+
+            assert_eq!(poll.void_poll(), Err(Error::InvalidPhase));
+        }
+
+        #[ink::test]
+        fn void_poll_after_tallying_deadline_voids_poll() {
+            let vote_duration: u32 = 60;
+            let mut poll = new_test_poll(vote_duration);
+            poll.phase = PollPhase::Tallying;
+
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(u64::from(
+                (vote_duration + TEST_PROCESSING_DURATION) * 1000 + 1,
+            ));
+
+            assert_eq!(poll.void_poll(), Err(Error::DeadlineNotPassed));
+
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(u64::from(
+                (vote_duration + TEST_PROCESSING_DURATION + TEST_TALLYING_DURATION) * 1000 + 1,
+            ));
+
+            assert!(poll.void_poll().is_ok());
+            assert_eq!(poll.get_phase(), PollPhase::Voided);
+
+            let proof = [123; 1040];
+            let result = poll.tally_votes(proof, [3; 32]);
+
+            assert_eq!(result, Err(Error::InvalidPhase));
+        }
+
+        #[ink::test]
+        fn merge_message_tree_before_voting_period_ends_returns_error() {
+            let mut poll = new_test_poll(60);
+
+            assert_eq!(
+                poll.merge_message_subroots(1),
+                Err(Error::VotingPeriodNotEnded)
+            );
+
+            let result = poll.merge_message_tree();
+
+            assert!(result.is_err());
+            assert_eq!(result, Err(Error::VotingPeriodNotEnded));
+        }
+
+        #[ink::test]
+        fn merge_message_tree_before_subroots_are_merged_returns_error() {
+            let vote_duration: u32 = 60;
+            let mut poll = new_test_poll(vote_duration);
+
+            let msg = Message::new(1, vec![[2; 32], [3; 32]]);
+            let upk = test_public_key(1);
+            for _ in 0..9 {
+                poll.publish_message(msg.clone(), upk).unwrap();
+            }
+
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(u64::from(
+                vote_duration * 1000 + 1,
+            ));
+
+            // 9 messages fill 3 subtrees of 4 leaves
+            assert_eq!(poll.merge_message_subroots(2), Ok(1));
+            assert_eq!(poll.merge_message_tree(), Err(Error::SubrootsNotMerged));
+            assert_eq!(poll.get_phase(), PollPhase::MergingTrees);
+
+            assert_eq!(poll.merge_message_subroots(2), Ok(0));
+            assert_eq!(poll.merge_message_tree(), Ok(()));
+            assert_eq!(poll.get_phase(), PollPhase::Processing);
+        }
+
+        #[ink::test]
+        fn merge_message_tree_emits_phase_changed_events() {
+            let vote_duration: u32 = 60;
+            let mut poll = new_test_poll(vote_duration);
+
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(u64::from(
+                vote_duration * 1000 + 1,
+            ));
+
+            let result = poll.merge_message_tree();
+
+            assert!(result.is_ok());
+            assert_eq!(poll.get_phase(), PollPhase::Processing);
+
+            let events = ink_env::test::recorded_events().collect::<Vec<_>>();
+
+            let phases: Vec<PollPhase> = events
+                .iter()
+                .map(|event| {
+                    let decoded_event = <Event as scale::Decode>::decode(&mut &event.data[..])
+                        .expect("encountered invalid contract event data buffer");
+                    if let Event::PhaseChanged(PhaseChanged { phase }) = decoded_event {
+                        phase
+                    } else {
+                        panic!("encountered unexpected event kind: expected a PhaseChanged event")
+                    }
+                })
+                .collect();
+
+            assert_eq!(phases, vec![PollPhase::MergingTrees, PollPhase::Processing]);
+        }
+
+        #[ink::test]
+        fn process_messages_before_trees_are_merged_returns_error() {
+            let vote_duration: u32 = 60;
+            let mut poll = new_test_poll(vote_duration);
+
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(u64::from(
+                vote_duration * 1000 + 1,
+            ));
+
+            let proof = [123; 1040];
+            let new_state_root: TreeRoot = [2; 32];
+            let result = poll.process_messages(proof, 0, new_state_root);
+
+            assert!(result.is_err());
+            assert_eq!(result, Err(Error::TreesNotMerged));
+        }
+
+        #[ink::test]
+        fn process_messages_out_of_order_batch_returns_error() {
+            let vote_duration: u32 = 60;
+            let mut poll = new_test_poll(vote_duration);
+
+            let msg = Message::new(1, vec![[2; 32], [3; 32]]);
+            let upk = test_public_key(1);
+            for _ in 0..2 * TEST_MESSAGE_BATCH_SIZE {
+                poll.publish_message(msg.clone(), upk).unwrap();
+            }
+
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(u64::from(
+                vote_duration * 1000 + 1,
+            ));
+
+            poll.merge_message_subroots(u32::MAX).unwrap();
+            poll.merge_message_tree().unwrap();
+
+            let proof = [123; 1040];
+            let new_state_root: TreeRoot = [2; 32];
+            let result = poll.process_messages(proof, TEST_MESSAGE_BATCH_SIZE, new_state_root);
+
+            assert!(result.is_err());
+            assert_eq!(result, Err(Error::BatchOutOfOrder));
+            assert_eq!(poll.get_processed_messages_count(), 0);
+        }
+
+        #[ink::test]
+        fn process_messages_with_invalid_proof_returns_error() {
+            let vote_duration: u32 = 60;
+            let mut poll = new_test_poll(vote_duration);
+
+            ink_env::test::set_block_timestamp::<ink_env::DefaultEnvironment>(u64::from(
+                vote_duration * 1000 + 1,
+            ));
+
+            poll.merge_message_subroots(u32::MAX).unwrap();
+            poll.merge_message_tree().unwrap();
+
+            let proof = [123; 1040];
+            let new_state_root: TreeRoot = [2; 32];
+            let result = poll.process_messages(proof, 0, new_state_root);
+
+            assert!(result.is_err());
+            assert_eq!(result, Err(Error::InvalidProof));
+            assert_eq!(poll.get_processed_messages_count(), 0);
+            assert_eq!(poll.get_phase(), PollPhase::Processing);
+        }
+
+        /// Root of a complete quinary tree of `leaves`.
+        fn quinary_root(leaves: &[BlsScalar]) -> BlsScalar {
+            let mut nodes = leaves.to_vec();
+            while nodes.len() > 1 {
+                nodes = nodes.chunks(5).map(sponge::hash).collect();
+            }
+
+            nodes[0]
+        }
+
+        #[ink::test]
+        fn tally_votes_with_valid_proof_publishes_tally() {
+            let mut poll = new_test_poll(60);
+            poll.phase = PollPhase::Tallying;
+            poll.number_sign_ups = TALLY_BATCH_SIZE as u32;
+
+            // The circuit is compiled off-chain over the public parameters, the poll only stores its verifier key
+            let public_parameters = PublicParameters::setup(1 << 17, &mut OsRng)
+                .unwrap()
+                .to_var_bytes();
+            poll.vote_tally_verifier_key
+                .set(&vote_tally_verifier_key(&public_parameters).unwrap());
+
+            // The first user voted for two vote options, the others did not vote
+            let mut ballot = vec![0; NUMBER_OF_VOTE_OPTIONS];
+            ballot[1] = 3;
+            ballot[7] = 2;
+            let vote_weights: Vec<BlsScalar> = ballot.iter().map(|w| BlsScalar::from(*w)).collect();
+
+            let state_leaves: Vec<TalliedStateLeaf> = (0..TALLY_BATCH_SIZE)
+                .map(|i| {
+                    let ballot = if i == 0 { ballot.clone() } else { Vec::new() };
+                    let vote_option_tree_root = if i == 0 {
+                        quinary_root(&vote_weights)
+                    } else {
+                        BlsScalar::zero()
+                    };
+
+                    TalliedStateLeaf {
+                        state_leaf: Some(StateLeafOpening {
+                            public_key: test_public_key(i as u64 + 1),
+                            voice_credit_balance: if i == 0 { 87 } else { 100 },
+                            vote_option_tree_root,
+                            nonce: BlsScalar::from(u64::from(i == 0)),
+                        }),
+                        ballot,
+                    }
+                })
+                .collect();
+
+            // The batch is the first subtree of the state tree, the other subtrees are empty
+            let leaf_hashes: Vec<BlsScalar> = state_leaves
+                .iter()
+                .map(|leaf| {
+                    let leaf = leaf.state_leaf.unwrap();
+                    sponge::hash(&[
+                        leaf.public_key.x,
+                        leaf.public_key.y,
+                        u128_to_scalar(leaf.voice_credit_balance),
+                        leaf.vote_option_tree_root,
+                        leaf.nonce,
+                    ])
+                })
+                .collect();
+            let state_path = vec![vec![BlsScalar::zero(); 4]; STATE_TREE_DEPTH - TALLY_BATCH_DEPTH];
+            let state_root =
+                state_path
+                    .iter()
+                    .fold(quinary_root(&leaf_hashes), |node, siblings| {
+                        let mut children = vec![node];
+                        children.extend_from_slice(siblings);
+                        sponge::hash(&children)
+                    });
+            poll.state_root = scalar_to_bytes(state_root);
+
+            let mut results = vec![0; NUMBER_OF_VOTE_OPTIONS];
+            results[1] = 3;
+            results[7] = 2;
+            let tally = Tally {
+                results: results.clone(),
+                total_spent_voice_credits: 13,
+                salt: BlsScalar::from(42),
+            };
+            let tally_commitment = scalar_to_bytes(tally.commitment());
+
+            let proof = prove_vote_tally(
+                &public_parameters,
+                &state_leaves,
+                &state_path,
+                &Tally {
+                    results: vec![0; NUMBER_OF_VOTE_OPTIONS],
+                    ..Tally::default()
+                },
+                tally.salt,
+                poll.state_root,
+                0,
+                [0; 32],
+                tally_commitment,
+            )
+            .unwrap();
+
+            assert_eq!(poll.tally_votes(proof, [3; 32]), Err(Error::InvalidProof));
+            assert_eq!(poll.tally_votes(proof, tally_commitment), Ok(()));
+            assert_eq!(poll.tally_commitment, Some(tally_commitment));
+
+            assert_eq!(
+                poll.publish_tally_results(results.clone(), 13, scalar_to_bytes(tally.salt)),
+                Ok(())
+            );
+            assert_eq!(poll.get_tally_results(), Some(results));
+        }
     }
 }
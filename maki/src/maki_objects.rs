@@ -1,13 +1,16 @@
 #[cfg(feature = "std")]
 use ink::storage::traits::StorageLayout;
 
-use crate::maki_types::{PublicKey, VoteOptionTreeRoot};
+use ink::prelude::vec::Vec;
+use ink::primitives::AccountId;
+
+use crate::maki_types::{PublicKey, VoiceCreditBalance, VoteOptionTreeRoot};
 
 #[derive(scale::Encode, scale::Decode)]
 #[cfg_attr(feature = "std",derive(scale_info::TypeInfo, StorageLayout))]
 pub struct StateLeaf {
     pub public_key: PublicKey,
-    pub voice_credit_balance: u16,
+    pub voice_credit_balance: VoiceCreditBalance,
     pub vote_option_tree_root: VoteOptionTreeRoot,
     pub nounce: [u8; 32],
 }
@@ -15,7 +18,7 @@ pub struct StateLeaf {
 impl StateLeaf {
     pub fn new(
         public_key: PublicKey,
-        voice_credit_balance: u16,
+        voice_credit_balance: VoiceCreditBalance,
         vote_option_tree_root: VoteOptionTreeRoot,
         nounce: [u8; 32],
     ) -> Self {
@@ -29,21 +32,72 @@ impl StateLeaf {
 }
 
 
+pub use maki_shared::command::{MESSAGE_DATA_MAX_LENGTH, VOTE_MESSAGE_TYPE};
+
+/// Depth of the subtrees messages are accumulated in before being merged into the message tree.
+pub const MESSAGE_TREE_SUB_DEPTH: u8 = 2;
+
 // TODO see : https://github.com/727-Ventures/ink/tree/feature/storage-docs/examples/complex-storage-structures
+/// Encrypted command published by a user. `data` holds the ciphertext field elements
+/// and `message_type` tells the coordinator how to interpret the decrypted command. Only messages of type
+/// `VOTE_MESSAGE_TYPE` are processed, the others leave the state unchanged.
 #[derive(
     scale::Encode,
     scale::Decode,
     PartialEq,
     Clone,
-    Copy,
 )]
 #[cfg_attr(feature = "std", derive(Debug, scale_info::TypeInfo,StorageLayout))]
 pub struct Message {
-    pub data: [u8; 32],
+    pub message_type: u8,
+    pub data: Vec<[u8; 32]>,
 }
 
 impl Message {
-    pub fn new(data: [u8; 32]) -> Self {
-        Message { data }
+    pub fn new(message_type: u8, data: Vec<[u8; 32]>) -> Self {
+        Message { message_type, data }
     }
 }
+
+/// Phases a poll goes through, in order. The voting phase ends at its deadline, the next phases follow the
+/// merge of the message tree and the proofs of the coordinator. A poll whose coordinator misses the processing
+/// or the tallying deadline is voided instead of reaching the `Finalized` phase.
+///
+/// There is no sign-up phase: users sign up once to the registry, whose state tree is shared by the polls and
+/// snapshotted when a poll is deployed, so polls start directly with the voting phase.
+#[derive(scale::Encode, scale::Decode, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "std", derive(Debug, scale_info::TypeInfo, StorageLayout))]
+pub enum PollPhase {
+    Voting,
+    MergingTrees,
+    Processing,
+    Tallying,
+    Finalized,
+    Voided,
+}
+
+/// Rules deciding which accounts are allowed to sign up.
+#[derive(scale::Encode, scale::Decode, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "std", derive(Debug, scale_info::TypeInfo, StorageLayout))]
+pub enum SignUpGatekeeper {
+    // Any account can sign up any number of times
+    Open,
+    // Only the accounts added to the allowlist by the owner can sign up
+    Allowlist,
+    // Any account can sign up, but only once
+    OneSignUpPerAccount,
+    // The contract at the given address approves each sign up through its `register` message
+    Contract(AccountId),
+}
+
+/// Source of the voice credits granted to a user when signing up.
+#[derive(scale::Encode, scale::Decode, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "std", derive(Debug, scale_info::TypeInfo, StorageLayout))]
+pub enum VoiceCreditProxy {
+    // Every user is granted the same amount of voice credits
+    Constant(VoiceCreditBalance),
+    // Users are granted the amount of voice credits uploaded by the owner for their account
+    Table,
+    // Users are granted their balance of the PSP22 token at the given address
+    Psp22(AccountId),
+}
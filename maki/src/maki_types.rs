@@ -5,3 +5,7 @@ pub type PublicKey = SharedTypePublicKey;
 pub type HashedLeaf = [u8; 32];
 
 pub type VoteOptionTreeRoot = [u8; 32];
+
+pub type VoiceCreditBalance = u128;
+
+pub type PollId = u32;
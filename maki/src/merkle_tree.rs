@@ -1,89 +1,137 @@
-use crate::hasher::hasher::hash_left_right;
-use hex_literal::hex;
+use crate::hasher::hash_children;
 #[cfg(feature = "std")]
 use ink::storage::traits::StorageLayout;
 use maki_shared::types::TreeRoot;
 
 use crate::maki_types::HashedLeaf;
+use core::ops::Range;
+use ink_prelude::vec::Vec;
 
-const MERKLE_TREE_MAX_DEPTH: usize = 32;
+pub(crate) const MERKLE_TREE_MAX_DEPTH: usize = 32;
+
+/// Number of recent roots a tree remembers, including the current one.
+pub const MERKLE_TREE_ROOT_HISTORY_SIZE: usize = 30;
 
 #[cfg(test)]
 pub const MERKLE_TREE_DEFAULT_DEPTH: usize = 24;
 
-const MERKLE_TREE_ZEROS: [[u8; 32]; MERKLE_TREE_MAX_DEPTH + 1] = [
-    hex!("7b7fd692a95c21575ccf41a10c5fffa47e231e9b6503551fc397a2024e7d1b45"), // BLAKE2b-256 Hash of "maki"
-    hex!("b6147a8cd8c6b3787b46234f3a2c7b97600079d9c61b00552c7bbba3e742fbb8"),
-    hex!("eb2e77cbae50f3ee70f9cde778e8c425247f960b2ba4878e445ca67de0436296"),
-    hex!("3e8ec789403271e32b46079812fc9ca4ac41028d7b71fa8c45655524a1e6b991"),
-    hex!("725de40a39b430101bd9a6991b708fd1939f4d8c81dcf964417fc40505aad5d2"),
-    hex!("840f5334075ea1407cb7bdc83c5a5181a5e6b09f4211d3253017212536ba2e78"),
-    hex!("1cf104a27dc2700808404b54b37544b74890dd23135e315101771774c1edf206"),
-    hex!("5696ffe94c052fb985d13c90b70e359673ac1c0e5968ead90b40ac0b08558022"),
-    hex!("027013bd2beb5842480edbb208be94e05b82e071aedfa7c60e481662e5187e8e"),
-    hex!("6274865509d2c90b8e0dc801a3774e7bd01780293671e79b1a783be1bc697778"),
-    hex!("9ffa205b504ddf0d9bd4c61fc69fec9918116ad9e6e0561a6fb131b27a73fb51"),
-    hex!("7ca83dd95c0119d9ebf97159a9f0c08250f4ae0720b0e12e365a3c78aabd7476"),
-    hex!("1f36392825503141d49391e3059d88223cb6cc152743b58933ca60280a124ccd"),
-    hex!("ededfc17fa461c1176d38e11a0a81bc99983722ac4c422741e599a34277189d7"),
-    hex!("ae90d8bb755683f4f8a0efbf2766e2594a7d704bba660ed5535080d154ec397d"),
-    hex!("05c8bc0a2b656015a1d8a313fcc868fc53a5affa0dd6d7b3449b14d8da6a3e09"),
-    hex!("6acbd2fdf1f4494042c6ac1ae642ae9ecb70c8d4e39534235de8fe92d8406875"),
-    hex!("18233d5e1babb0c8faa1bc48ef780657daa4caa0b0db469a19ae399c925d46af"),
-    hex!("f5d5151bbeaf611f80c57afb51956224d5487d2d85c59cd62890cfea57991619"),
-    hex!("b28bb4b2acc11e42e3245489a5badb867e4a8b8a01a048b00339736dc9a32dbf"),
-    hex!("1640e5419648d7e1c73aac2878bcc7d959c9268c66d26a9b360db4976a4b72ee"),
-    hex!("3ce95dd8a4931b136c78c2a9c7c985499197acd18c5dd19a589e72df6529fb84"),
-    hex!("31c750a39f3cf8dffd1bacb42f0f094d664b4ca4a5d967c0581d1979502675a4"),
-    hex!("d1066747a49c9e4ce0c5fb798d978e65d73f63bfd542ffe01941715ac46717d5"),
-    hex!("bbfd8dbdc7c1775369473e139674cefae589abb2078500cf6f69db26c8b2a1b2"),
-    hex!("bee3f9931bcddd77287493c45126df07b5f8b585593b9f516812af6c0e492092"),
-    hex!("12b9b47543cb6da4406e7b28f3a0d14ec0f2393ffc8407280238d5bfb13c7cd0"),
-    hex!("13cc72a87cf878ed7ada1f2f4da9fbb6b5a76451ccfbd294505f090c563037bb"),
-    hex!("ae6db4aba7b5dd131e5c484c78a506299411edde24adfc0f06ecb48ffce8814c"),
-    hex!("3f65cc50530d9e8270ea39293255e62d212fb5023ea885700c6d5e4c92c0fcb8"),
-    hex!("78f8bbc4497fe45f495315ccd94e7b559f9dbd2bf81dfdae0787a54f6ea93022"),
-    hex!("641bbc9668167e04bf60c515f84b8f61fd6cf60c60edeb9b0bab771843fe19fa"),
-    hex!("ae91edaddca5e8e616541b5ea8809722a4aa3a65b748ed7a4b487229867b3dd0"),
-];
+// Zero values of the binary and quinary trees, `MERKLE_TREE_BINARY_ZEROS` and `MERKLE_TREE_QUINARY_ZEROS`.
+// They are derived from a seed with the node hash by the build script.
+include!(concat!(env!("OUT_DIR"), "/merkle_tree_zeros.rs"));
 
+/// Returns the zero values of the trees of arity `arity`, only binary and quinary trees are supported.
+pub(crate) const fn merkle_tree_zeros(arity: usize) -> &'static [[u8; 32]; MERKLE_TREE_MAX_DEPTH + 1] {
+    match arity {
+        2 => &MERKLE_TREE_BINARY_ZEROS,
+        5 => &MERKLE_TREE_QUINARY_ZEROS,
+        _ => panic!("unsupported Merkle tree arity"),
+    }
+}
+
+/// Incremental Merkle tree of arity `ARITY`, storing only the last nodes inserted at each level.
 #[derive(scale::Encode, scale::Decode, PartialEq)]
-#[cfg_attr(feature = "std", derive(Debug, scale_info::TypeInfo, StorageLayout))]
-pub struct MerkleTree {
+#[cfg_attr(feature = "std", derive(Debug, scale_info::TypeInfo))]
+pub struct MerkleTree<const ARITY: usize = 2> {
     tree_depth: u8,
     next_leaf_index: u128,
-    filled_subtrees: [[u8; 32]; MERKLE_TREE_MAX_DEPTH],
+    /// Children of the last node updated at each level.
+    filled_subtrees: [[[u8; 32]; ARITY]; MERKLE_TREE_MAX_DEPTH],
     root: TreeRoot,
+    /// Ring buffer of the last roots, `root_history_index` is the position of the current root.
+    root_history: [TreeRoot; MERKLE_TREE_ROOT_HISTORY_SIZE],
+    root_history_index: u32,
 }
 
+// Storage layout is not derived, ink! implements it only for arrays of a given size
+#[cfg(feature = "std")]
+impl<const ARITY: usize> StorageLayout for MerkleTree<ARITY> {
+    fn layout(key: &ink::primitives::Key) -> ink::metadata::layout::Layout {
+        ink::metadata::layout::Layout::Leaf(ink::metadata::layout::LeafLayout::from_key::<Self>(
+            ink::metadata::layout::LayoutKey::from(key),
+        ))
+    }
+}
+
+pub type BinaryMerkleTree = MerkleTree<2>;
+
+pub type QuinaryMerkleTree = MerkleTree<5>;
+
 #[derive(Debug, PartialEq)]
 pub enum MerkleTreeError {
     InvalidTreeDepth,
     TreeIsFull,
+    LeafNotFound,
+    InvalidPath,
+    QueueAlreadyMerging,
+    SubrootsNotMerged,
+}
+
+/// Inclusion proof of a leaf, from the leaf level up to the root.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MerklePath {
+    /// Hashes of the siblings of the node at each level, from left to right.
+    pub siblings: Vec<Vec<[u8; 32]>>,
+    /// Position of the node among its siblings at each level, i.e. the digits of the leaf index in base arity.
+    pub path_indices: Vec<u8>,
+}
+
+/// Verifies that `leaf` is included in the tree of root `root`, the arity of the tree is given by the path.
+pub fn verify_path(root: &TreeRoot, leaf: &HashedLeaf, path: &MerklePath) -> bool {
+    if path.siblings.len() != path.path_indices.len() {
+        return false;
+    }
+
+    let mut node = *leaf;
+
+    for (siblings, index) in path.siblings.iter().zip(path.path_indices.iter()) {
+        let index = usize::from(*index);
+
+        if index > siblings.len() {
+            return false;
+        }
+
+        let mut children = siblings.clone();
+        children.insert(index, node);
+
+        node = hash_children(&children);
+    }
+
+    node == *root
+}
+
+/// Capacity of a tree of arity `arity` and depth `tree_depth`.
+pub(crate) fn max_leaves(arity: usize, tree_depth: u8) -> u128 {
+    (arity as u128).pow(u32::from(tree_depth))
 }
 
-impl MerkleTree {
+impl<const ARITY: usize> MerkleTree<ARITY> {
+    const ZEROS: &'static [[u8; 32]; MERKLE_TREE_MAX_DEPTH + 1] = merkle_tree_zeros(ARITY);
+
     pub fn new(tree_depth: u8) -> Result<Self, MerkleTreeError> {
-        if tree_depth <= 0 || usize::from(tree_depth) > MERKLE_TREE_MAX_DEPTH {
+        if tree_depth == 0 || usize::from(tree_depth) > MERKLE_TREE_MAX_DEPTH {
             return Err(MerkleTreeError::InvalidTreeDepth);
         }
 
-        let zeros = MERKLE_TREE_ZEROS;
+        let zeros = Self::ZEROS;
 
         // pre-fill subtree with zeros.
-        let mut filled_subtrees = [[Default::default(); 32]; MERKLE_TREE_MAX_DEPTH];
-        filled_subtrees.copy_from_slice(&zeros[0..32]);
+        let mut filled_subtrees = [[[Default::default(); 32]; ARITY]; MERKLE_TREE_MAX_DEPTH];
+        for (children, zero) in filled_subtrees.iter_mut().zip(zeros.iter()) {
+            *children = [*zero; ARITY];
+        }
 
         Ok(MerkleTree {
             tree_depth,
             next_leaf_index: 0,
             filled_subtrees,
             root: zeros[usize::from(tree_depth)],
+            root_history: [zeros[usize::from(tree_depth)]; MERKLE_TREE_ROOT_HISTORY_SIZE],
+            root_history_index: 0,
         })
     }
 
     pub fn insert_leaf(&mut self, leaf: HashedLeaf) -> Result<u128, MerkleTreeError> {
-        if self.next_leaf_index >= 1 << self.tree_depth {
+        if self.next_leaf_index >= max_leaves(ARITY, self.tree_depth) {
             return Err(MerkleTreeError::TreeIsFull);
         }
 
@@ -92,33 +140,277 @@ impl MerkleTree {
 
         for i in 0..self.tree_depth {
             let index = usize::from(i);
-            let left;
-            let right;
+            let position = (current_leaf_index % ARITY as u128) as usize;
 
-            if current_leaf_index % 2 == 0 {
-                right = MERKLE_TREE_ZEROS[index];
-                left = current_level_hash;
+            // Nodes on the right of the inserted one are still empty
+            let mut children = [Self::ZEROS[index]; ARITY];
+            children[..position].copy_from_slice(&self.filled_subtrees[index][..position]);
+            children[position] = current_level_hash;
 
-                self.filled_subtrees[index] = current_level_hash;
-            } else {
-                left = self.filled_subtrees[index];
-                right = current_level_hash;
-            }
+            self.filled_subtrees[index][position] = current_level_hash;
 
-            current_leaf_index /= 2;
-            current_level_hash = hash_left_right(&left, &right);
+            current_leaf_index /= ARITY as u128;
+            current_level_hash = hash_children(&children);
         }
 
-        self.root = current_level_hash;
+        self.set_root(current_level_hash);
 
         self.next_leaf_index += 1;
 
-        return Ok(self.next_leaf_index);
+        Ok(self.next_leaf_index)
+    }
+
+    /// Inserts `leaves` after the last inserted leaf and returns the range of indices they were assigned.
+    /// Ancestors shared by several leaves are hashed once, the root is the same as inserting the leaves one by one.
+    pub fn insert_leaves(
+        &mut self,
+        leaves: &[HashedLeaf],
+    ) -> Result<Range<u128>, MerkleTreeError> {
+        let first_leaf_index = self.next_leaf_index;
+        let end_leaf_index = first_leaf_index + leaves.len() as u128;
+
+        if end_leaf_index > max_leaves(ARITY, self.tree_depth) {
+            return Err(MerkleTreeError::TreeIsFull);
+        }
+
+        if leaves.is_empty() {
+            return Ok(first_leaf_index..end_leaf_index);
+        }
+
+        // Nodes of the current level that changed, the first one being at index `first_index`
+        let mut first_index = first_leaf_index;
+        let mut nodes = leaves.to_vec();
+
+        for i in 0..self.tree_depth {
+            let index = usize::from(i);
+            let first_parent_index = first_index / ARITY as u128;
+            let last_parent_index = (first_index + nodes.len() as u128 - 1) / ARITY as u128;
+
+            let mut parents = Vec::with_capacity((last_parent_index - first_parent_index + 1) as usize);
+
+            for parent_index in first_parent_index..=last_parent_index {
+                // Nodes on the right of the inserted ones are still empty
+                let mut children = [Self::ZEROS[index]; ARITY];
+
+                for (position, child) in children.iter_mut().enumerate() {
+                    let child_index = parent_index * ARITY as u128 + position as u128;
+
+                    if child_index < first_index {
+                        *child = self.filled_subtrees[index][position];
+                    } else if let Some(node) = nodes.get((child_index - first_index) as usize) {
+                        *child = *node;
+                        self.filled_subtrees[index][position] = *node;
+                    }
+                }
+
+                parents.push(hash_children(&children));
+            }
+
+            first_index = first_parent_index;
+            nodes = parents;
+        }
+
+        self.set_root(nodes[0]);
+
+        self.next_leaf_index = end_leaf_index;
+
+        Ok(first_leaf_index..end_leaf_index)
+    }
+
+    /// Replaces the leaf at `leaf_index`, `path` must be the inclusion proof of `old_leaf` in the current tree.
+    pub fn update_leaf(
+        &mut self,
+        leaf_index: u128,
+        old_leaf: HashedLeaf,
+        new_leaf: HashedLeaf,
+        path: &MerklePath,
+    ) -> Result<(), MerkleTreeError> {
+        if leaf_index >= self.next_leaf_index {
+            return Err(MerkleTreeError::LeafNotFound);
+        }
+
+        let mut remaining_index = leaf_index;
+        let path_matches_index = path.path_indices.len() == usize::from(self.tree_depth)
+            && path.siblings.iter().all(|siblings| siblings.len() == ARITY - 1)
+            && path.path_indices.iter().all(|index| {
+                let matches = u128::from(*index) == remaining_index % ARITY as u128;
+                remaining_index /= ARITY as u128;
+                matches
+            });
+
+        if !path_matches_index || !verify_path(&self.root, &old_leaf, path) {
+            return Err(MerkleTreeError::InvalidPath);
+        }
+
+        // Parent of the last inserted node at each level, filled subtrees hold its children.
+        let mut last_parent_index = (self.next_leaf_index - 1) / ARITY as u128;
+
+        let mut current_leaf_index = leaf_index;
+        let mut current_level_hash = new_leaf;
+
+        for (i, siblings) in path.siblings.iter().enumerate() {
+            let position = (current_leaf_index % ARITY as u128) as usize;
+            current_leaf_index /= ARITY as u128;
+
+            if current_leaf_index == last_parent_index {
+                self.filled_subtrees[i][position] = current_level_hash;
+            }
+            last_parent_index /= ARITY as u128;
+
+            let mut children = siblings.clone();
+            children.insert(position, current_level_hash);
+
+            current_level_hash = hash_children(&children);
+        }
+
+        self.set_root(current_level_hash);
+
+        Ok(())
     }
 
     pub fn get_root(&self) -> [u8; 32] {
         self.root
     }
+
+    /// Whether `root` is one of the last `MERKLE_TREE_ROOT_HISTORY_SIZE` roots of the tree.
+    pub fn is_known_root(&self, root: &TreeRoot) -> bool {
+        self.root_history.contains(root)
+    }
+
+    fn set_root(&mut self, root: TreeRoot) {
+        self.root_history_index =
+            (self.root_history_index + 1) % MERKLE_TREE_ROOT_HISTORY_SIZE as u32;
+        self.root_history[self.root_history_index as usize] = root;
+
+        self.root = root;
+    }
+}
+
+/// Off-chain Merkle tree storing every node, so that inclusion proofs can be generated.
+/// It has the same roots as `MerkleTree` of the same arity for the same leaves.
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, PartialEq)]
+pub struct FullMerkleTree<const ARITY: usize = 2> {
+    tree_depth: u8,
+    /// Nodes of each level, from the leaves to the root. Missing nodes are the zero value of their level.
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+#[cfg(feature = "std")]
+impl<const ARITY: usize> FullMerkleTree<ARITY> {
+    const ZEROS: &'static [[u8; 32]; MERKLE_TREE_MAX_DEPTH + 1] = merkle_tree_zeros(ARITY);
+
+    pub fn new(tree_depth: u8) -> Result<Self, MerkleTreeError> {
+        if tree_depth == 0 || usize::from(tree_depth) > MERKLE_TREE_MAX_DEPTH {
+            return Err(MerkleTreeError::InvalidTreeDepth);
+        }
+
+        Ok(FullMerkleTree {
+            tree_depth,
+            levels: vec![Vec::new(); usize::from(tree_depth) + 1],
+        })
+    }
+
+    pub fn insert_leaf(&mut self, leaf: HashedLeaf) -> Result<u128, MerkleTreeError> {
+        let leaf_index = self.levels[0].len();
+
+        if leaf_index as u128 >= max_leaves(ARITY, self.tree_depth) {
+            return Err(MerkleTreeError::TreeIsFull);
+        }
+
+        self.levels[0].push(leaf);
+        self.update_parents(leaf_index);
+
+        Ok(self.levels[0].len() as u128)
+    }
+
+    pub fn get_root(&self) -> [u8; 32] {
+        self.get_node(usize::from(self.tree_depth), 0)
+    }
+
+    pub fn get_leaf(&self, leaf_index: u128) -> Option<HashedLeaf> {
+        usize::try_from(leaf_index)
+            .ok()
+            .and_then(|index| self.levels[0].get(index).copied())
+    }
+
+    pub fn get_number_of_leaves(&self) -> u128 {
+        self.levels[0].len() as u128
+    }
+
+    /// Replaces the leaf at `leaf_index`.
+    pub fn update_leaf(
+        &mut self,
+        leaf_index: u128,
+        new_leaf: HashedLeaf,
+    ) -> Result<(), MerkleTreeError> {
+        if self.get_leaf(leaf_index).is_none() {
+            return Err(MerkleTreeError::LeafNotFound);
+        }
+
+        self.levels[0][leaf_index as usize] = new_leaf;
+        self.update_parents(leaf_index as usize);
+
+        Ok(())
+    }
+
+    /// Returns the inclusion proof of the leaf at `leaf_index`.
+    pub fn get_path(&self, leaf_index: u128) -> Result<MerklePath, MerkleTreeError> {
+        if self.get_leaf(leaf_index).is_none() {
+            return Err(MerkleTreeError::LeafNotFound);
+        }
+
+        let mut index = leaf_index as usize;
+        let mut siblings = Vec::with_capacity(usize::from(self.tree_depth));
+        let mut path_indices = Vec::with_capacity(usize::from(self.tree_depth));
+
+        for level in 0..usize::from(self.tree_depth) {
+            let first_child = index - index % ARITY;
+
+            siblings.push(
+                (first_child..first_child + ARITY)
+                    .filter(|child| *child != index)
+                    .map(|child| self.get_node(level, child))
+                    .collect(),
+            );
+            path_indices.push((index % ARITY) as u8);
+
+            index /= ARITY;
+        }
+
+        Ok(MerklePath {
+            siblings,
+            path_indices,
+        })
+    }
+
+    /// Recomputes the ancestors of the leaf at `leaf_index`.
+    fn update_parents(&mut self, leaf_index: usize) {
+        let mut index = leaf_index;
+
+        for level in 0..usize::from(self.tree_depth) {
+            let first_child = index - index % ARITY;
+            let children: Vec<[u8; 32]> = (first_child..first_child + ARITY)
+                .map(|child| self.get_node(level, child))
+                .collect();
+
+            index /= ARITY;
+            let parent = hash_children(&children);
+
+            if index < self.levels[level + 1].len() {
+                self.levels[level + 1][index] = parent;
+            } else {
+                self.levels[level + 1].push(parent);
+            }
+        }
+    }
+
+    fn get_node(&self, level: usize, index: usize) -> [u8; 32] {
+        self.levels[level]
+            .get(index)
+            .copied()
+            .unwrap_or(Self::ZEROS[level])
+    }
 }
 
 #[cfg(test)]
@@ -126,11 +418,16 @@ mod tests {
 
     use super::*;
 
+    use blake2::{Blake2b512, Digest};
+    use dusk_bls12_381::BlsScalar;
+    use maki_shared::functions_utils::scalar_to_bytes;
+    use proptest::prelude::*;
+
     const TEST_DEPTH: usize = 10;
 
     #[test]
     fn test_error_when_tree_is_full() {
-        let mut tree = MerkleTree::new(TEST_DEPTH as u8).unwrap();
+        let mut tree = BinaryMerkleTree::new(TEST_DEPTH as u8).unwrap();
 
         for i in 0..2usize.pow(TEST_DEPTH as u32) {
             tree.insert_leaf([(i % (2usize.pow(8)))as u8; 32]).unwrap();
@@ -141,18 +438,399 @@ mod tests {
         assert_eq!(err, Err(MerkleTreeError::TreeIsFull));
     }
 
+    #[test]
+    fn test_error_when_quinary_tree_is_full() {
+        let mut tree = QuinaryMerkleTree::new(3).unwrap();
+
+        for i in 0..5u8.pow(3) {
+            tree.insert_leaf([i; 32]).unwrap();
+        }
+
+        let err = tree.insert_leaf([2; 32]);
+
+        assert_eq!(err, Err(MerkleTreeError::TreeIsFull));
+    }
+
     #[test]
     fn test_error_when_tree_depth_exceeds_max() {
-        let tree = MerkleTree::new((MERKLE_TREE_MAX_DEPTH+1) as u8);
+        let tree = MerkleTree::<2>::new((MERKLE_TREE_MAX_DEPTH+1) as u8);
 
         assert!(tree.is_err());
         assert_eq!(tree, Err(MerkleTreeError::InvalidTreeDepth));
+
+        let tree = QuinaryMerkleTree::new((MERKLE_TREE_MAX_DEPTH+1) as u8);
+
+        assert_eq!(tree, Err(MerkleTreeError::InvalidTreeDepth));
     }
 
     #[test]
     fn test_empty_tree_root_is_first_zero_value() {
-        let tree = MerkleTree::new(TEST_DEPTH as u8).unwrap();
+        let tree = BinaryMerkleTree::new(TEST_DEPTH as u8).unwrap();
+
+        assert_eq!(tree.root, MERKLE_TREE_BINARY_ZEROS[TEST_DEPTH]);
+
+        let tree = QuinaryMerkleTree::new(TEST_DEPTH as u8).unwrap();
+
+        assert_eq!(tree.root, MERKLE_TREE_QUINARY_ZEROS[TEST_DEPTH]);
+    }
+
+    #[test]
+    fn test_first_zero_value_is_hash_of_seed() {
+        let seed = BlsScalar::from_bytes_wide(&Blake2b512::digest(b"maki").into());
+
+        assert_eq!(MERKLE_TREE_BINARY_ZEROS[0], scalar_to_bytes(seed));
+        assert_eq!(MERKLE_TREE_QUINARY_ZEROS[0], scalar_to_bytes(seed));
+    }
+
+    #[test]
+    fn test_zero_values_are_hashes_of_empty_children() {
+        for level in 0..MERKLE_TREE_MAX_DEPTH {
+            assert_eq!(
+                MERKLE_TREE_BINARY_ZEROS[level + 1],
+                hash_children(&[MERKLE_TREE_BINARY_ZEROS[level]; 2])
+            );
+            assert_eq!(
+                MERKLE_TREE_QUINARY_ZEROS[level + 1],
+                hash_children(&[MERKLE_TREE_QUINARY_ZEROS[level]; 5])
+            );
+        }
+    }
+
+    #[test]
+    fn test_insert_leaves_returns_assigned_indices() {
+        let mut tree = QuinaryMerkleTree::new(TEST_DEPTH as u8).unwrap();
+
+        assert_eq!(tree.insert_leaves(&[[1; 32], [2; 32]]), Ok(0..2));
+        assert_eq!(tree.insert_leaves(&[]), Ok(2..2));
+        assert_eq!(tree.insert_leaves(&[[3; 32]; 7]), Ok(2..9));
+        assert_eq!(tree.insert_leaf([4; 32]), Ok(10));
+    }
+
+    #[test]
+    fn test_insert_leaves_in_full_tree_returns_error() {
+        let mut tree = BinaryMerkleTree::new(2).unwrap();
+
+        tree.insert_leaves(&[[1; 32]; 3]).unwrap();
+        let root = tree.get_root();
+
+        assert_eq!(
+            tree.insert_leaves(&[[2; 32]; 2]),
+            Err(MerkleTreeError::TreeIsFull)
+        );
+        assert_eq!(tree.get_root(), root);
+        assert_eq!(tree.insert_leaves(&[[2; 32]]), Ok(3..4));
+    }
+
+    #[test]
+    fn test_last_roots_are_known() {
+        let mut tree = BinaryMerkleTree::new(TEST_DEPTH as u8).unwrap();
+        let mut roots = vec![tree.get_root()];
+
+        for i in 0..(MERKLE_TREE_ROOT_HISTORY_SIZE + 5) as u8 {
+            tree.insert_leaf([i; 32]).unwrap();
+            roots.push(tree.get_root());
+        }
+
+        let (forgotten_roots, known_roots) =
+            roots.split_at(roots.len() - MERKLE_TREE_ROOT_HISTORY_SIZE);
+
+        assert!(known_roots.iter().all(|root| tree.is_known_root(root)));
+        assert!(!forgotten_roots.iter().any(|root| tree.is_known_root(root)));
+        assert!(!tree.is_known_root(&[1; 32]));
+    }
+
+    #[test]
+    fn test_root_before_update_is_known() {
+        let mut tree = QuinaryMerkleTree::new(TEST_DEPTH as u8).unwrap();
+        let mut full_tree = FullMerkleTree::<5>::new(TEST_DEPTH as u8).unwrap();
+
+        tree.insert_leaf([1; 32]).unwrap();
+        full_tree.insert_leaf([1; 32]).unwrap();
+
+        let root = tree.get_root();
+        let path = full_tree.get_path(0).unwrap();
+
+        tree.update_leaf(0, [1; 32], [2; 32], &path).unwrap();
+
+        assert_ne!(tree.get_root(), root);
+        assert!(tree.is_known_root(&root));
+        assert!(tree.is_known_root(&tree.get_root()));
+    }
+
+    fn assert_full_tree_has_same_roots_as_incremental_tree<const ARITY: usize>() {
+        let mut tree = MerkleTree::<ARITY>::new(TEST_DEPTH as u8).unwrap();
+        let mut full_tree = FullMerkleTree::<ARITY>::new(TEST_DEPTH as u8).unwrap();
+
+        assert_eq!(full_tree.get_root(), tree.get_root());
+
+        for i in 0..30u8 {
+            assert_eq!(full_tree.insert_leaf([i; 32]), tree.insert_leaf([i; 32]));
+            assert_eq!(full_tree.get_root(), tree.get_root());
+        }
+    }
+
+    #[test]
+    fn test_full_tree_has_same_roots_as_incremental_tree() {
+        assert_full_tree_has_same_roots_as_incremental_tree::<2>();
+        assert_full_tree_has_same_roots_as_incremental_tree::<5>();
+    }
+
+    #[test]
+    fn test_quinary_tree_root_is_hash_of_five_children() {
+        let mut tree = QuinaryMerkleTree::new(1).unwrap();
+
+        for i in 0..3u8 {
+            tree.insert_leaf([i; 32]).unwrap();
+        }
+
+        let zero = MERKLE_TREE_QUINARY_ZEROS[0];
+        assert_eq!(
+            tree.get_root(),
+            hash_children(&[[0; 32], [1; 32], [2; 32], zero, zero])
+        );
+    }
+
+    fn assert_path_of_every_leaf_is_verified<const ARITY: usize>() {
+        let mut full_tree = FullMerkleTree::<ARITY>::new(TEST_DEPTH as u8).unwrap();
+
+        for i in 0..13u8 {
+            full_tree.insert_leaf([i; 32]).unwrap();
+        }
+
+        let root = full_tree.get_root();
+
+        for i in 0..13u8 {
+            let path = full_tree.get_path(u128::from(i)).unwrap();
+
+            assert_eq!(path.siblings.len(), TEST_DEPTH);
+            assert!(path.siblings.iter().all(|siblings| siblings.len() == ARITY - 1));
+            assert!(verify_path(&root, &[i; 32], &path));
+        }
+    }
+
+    #[test]
+    fn test_path_of_every_leaf_is_verified() {
+        assert_path_of_every_leaf_is_verified::<2>();
+        assert_path_of_every_leaf_is_verified::<5>();
+    }
+
+    fn assert_path_with_wrong_leaf_or_sibling_is_not_verified<const ARITY: usize>() {
+        let mut full_tree = FullMerkleTree::<ARITY>::new(TEST_DEPTH as u8).unwrap();
+
+        for i in 0..5u8 {
+            full_tree.insert_leaf([i; 32]).unwrap();
+        }
+
+        let root = full_tree.get_root();
+        let path = full_tree.get_path(3).unwrap();
 
-        assert_eq!(tree.root, MERKLE_TREE_ZEROS[usize::from(TEST_DEPTH)]);
+        assert!(!verify_path(&root, &[4; 32], &path));
+
+        let mut tampered_path = path.clone();
+        tampered_path.siblings[2][0] = [9; 32];
+        assert!(!verify_path(&root, &[3; 32], &tampered_path));
+
+        let mut tampered_path = path.clone();
+        tampered_path.path_indices[0] = 0;
+        assert!(!verify_path(&root, &[3; 32], &tampered_path));
+
+        let mut tampered_path = path;
+        tampered_path.path_indices[0] = ARITY as u8;
+        assert!(!verify_path(&root, &[3; 32], &tampered_path));
+    }
+
+    #[test]
+    fn test_path_with_wrong_leaf_or_sibling_is_not_verified() {
+        assert_path_with_wrong_leaf_or_sibling_is_not_verified::<2>();
+        assert_path_with_wrong_leaf_or_sibling_is_not_verified::<5>();
+    }
+
+    #[test]
+    fn test_path_of_missing_leaf_returns_error() {
+        let mut full_tree = FullMerkleTree::<2>::new(TEST_DEPTH as u8).unwrap();
+
+        full_tree.insert_leaf([1; 32]).unwrap();
+
+        assert_eq!(full_tree.get_path(1), Err(MerkleTreeError::LeafNotFound));
+    }
+
+    fn assert_update_leaf_with_invalid_path_returns_error<const ARITY: usize>() {
+        let mut tree = MerkleTree::<ARITY>::new(TEST_DEPTH as u8).unwrap();
+        let mut full_tree = FullMerkleTree::<ARITY>::new(TEST_DEPTH as u8).unwrap();
+
+        for i in 0..5u8 {
+            tree.insert_leaf([i; 32]).unwrap();
+            full_tree.insert_leaf([i; 32]).unwrap();
+        }
+
+        let root = tree.get_root();
+        let path = full_tree.get_path(3).unwrap();
+
+        assert_eq!(
+            tree.update_leaf(3, [4; 32], [9; 32], &path),
+            Err(MerkleTreeError::InvalidPath)
+        );
+        assert_eq!(
+            tree.update_leaf(2, [3; 32], [9; 32], &path),
+            Err(MerkleTreeError::InvalidPath)
+        );
+        assert_eq!(
+            tree.update_leaf(5, [0; 32], [9; 32], &path),
+            Err(MerkleTreeError::LeafNotFound)
+        );
+        assert_eq!(tree.get_root(), root);
+    }
+
+    #[test]
+    fn test_update_leaf_with_invalid_path_returns_error() {
+        assert_update_leaf_with_invalid_path_returns_error::<2>();
+        assert_update_leaf_with_invalid_path_returns_error::<5>();
+    }
+
+    #[test]
+    fn test_update_leaf_with_path_of_other_arity_returns_error() {
+        let mut tree = QuinaryMerkleTree::new(TEST_DEPTH as u8).unwrap();
+        let mut binary_tree = FullMerkleTree::<2>::new(TEST_DEPTH as u8).unwrap();
+
+        tree.insert_leaf([1; 32]).unwrap();
+        binary_tree.insert_leaf([1; 32]).unwrap();
+
+        let path = binary_tree.get_path(0).unwrap();
+
+        assert_eq!(
+            tree.update_leaf(0, [1; 32], [2; 32], &path),
+            Err(MerkleTreeError::InvalidPath)
+        );
+    }
+
+    #[test]
+    fn test_error_when_full_tree_is_full() {
+        let mut full_tree = FullMerkleTree::<2>::new(2).unwrap();
+
+        for i in 0..4u8 {
+            full_tree.insert_leaf([i; 32]).unwrap();
+        }
+
+        assert_eq!(full_tree.insert_leaf([4; 32]), Err(MerkleTreeError::TreeIsFull));
+
+        let mut full_tree = FullMerkleTree::<5>::new(2).unwrap();
+
+        for i in 0..25u8 {
+            full_tree.insert_leaf([i; 32]).unwrap();
+        }
+
+        assert_eq!(full_tree.insert_leaf([25; 32]), Err(MerkleTreeError::TreeIsFull));
+    }
+
+    /// Updates random leaves of both trees then checks their roots against a tree rebuilt from the final leaves.
+    fn check_update_leaf_matches_rebuilt_tree<const ARITY: usize>(
+        leaves: Vec<[u8; 32]>,
+        updates: Vec<(prop::sample::Index, [u8; 32])>,
+        appended_leaves: Vec<[u8; 32]>,
+    ) -> Result<(), TestCaseError> {
+        let mut tree = MerkleTree::<ARITY>::new(TEST_DEPTH as u8).unwrap();
+        let mut full_tree = FullMerkleTree::<ARITY>::new(TEST_DEPTH as u8).unwrap();
+        let mut expected_leaves = leaves.clone();
+
+        for leaf in leaves {
+            tree.insert_leaf(leaf).unwrap();
+            full_tree.insert_leaf(leaf).unwrap();
+        }
+
+        for (index, new_leaf) in updates {
+            let leaf_index = index.index(expected_leaves.len());
+            let path = full_tree.get_path(leaf_index as u128).unwrap();
+
+            tree.update_leaf(
+                leaf_index as u128,
+                expected_leaves[leaf_index],
+                new_leaf,
+                &path,
+            )
+            .unwrap();
+            full_tree.update_leaf(leaf_index as u128, new_leaf).unwrap();
+            expected_leaves[leaf_index] = new_leaf;
+
+            prop_assert_eq!(tree.get_root(), full_tree.get_root());
+        }
+
+        // Appending after updates must account for the updated leaves
+        for leaf in appended_leaves {
+            tree.insert_leaf(leaf).unwrap();
+            full_tree.insert_leaf(leaf).unwrap();
+            expected_leaves.push(leaf);
+        }
+
+        let mut rebuilt_tree = MerkleTree::<ARITY>::new(TEST_DEPTH as u8).unwrap();
+        for leaf in expected_leaves {
+            rebuilt_tree.insert_leaf(leaf).unwrap();
+        }
+
+        prop_assert_eq!(tree.get_root(), rebuilt_tree.get_root());
+        prop_assert_eq!(full_tree.get_root(), rebuilt_tree.get_root());
+
+        Ok(())
+    }
+
+    /// Inserts the same leaves in batches in a tree and one by one in another, then checks that the trees match.
+    fn check_insert_leaves_matches_insert_leaf<const ARITY: usize>(
+        batches: Vec<Vec<[u8; 32]>>,
+    ) -> Result<(), TestCaseError> {
+        let mut tree = MerkleTree::<ARITY>::new(TEST_DEPTH as u8).unwrap();
+        let mut batched_tree = MerkleTree::<ARITY>::new(TEST_DEPTH as u8).unwrap();
+
+        for batch in batches {
+            for leaf in batch.iter() {
+                tree.insert_leaf(*leaf).unwrap();
+            }
+
+            let end_leaf_index = batched_tree.next_leaf_index + batch.len() as u128;
+            prop_assert_eq!(
+                batched_tree.insert_leaves(&batch),
+                Ok(end_leaf_index - batch.len() as u128..end_leaf_index)
+            );
+
+            prop_assert_eq!(batched_tree.get_root(), tree.get_root());
+            prop_assert_eq!(batched_tree.filled_subtrees, tree.filled_subtrees);
+        }
+
+        Ok(())
+    }
+
+    proptest! {
+        // Hashing is slow in debug builds, keep the number of cases low
+        #![proptest_config(ProptestConfig::with_cases(16))]
+
+        #[test]
+        fn test_update_leaf_matches_rebuilt_binary_tree(
+            leaves in prop::collection::vec(any::<[u8; 32]>(), 1..10),
+            updates in prop::collection::vec((any::<prop::sample::Index>(), any::<[u8; 32]>()), 1..10),
+            appended_leaves in prop::collection::vec(any::<[u8; 32]>(), 0..5),
+        ) {
+            check_update_leaf_matches_rebuilt_tree::<2>(leaves, updates, appended_leaves)?;
+        }
+
+        #[test]
+        fn test_update_leaf_matches_rebuilt_quinary_tree(
+            leaves in prop::collection::vec(any::<[u8; 32]>(), 1..30),
+            updates in prop::collection::vec((any::<prop::sample::Index>(), any::<[u8; 32]>()), 1..10),
+            appended_leaves in prop::collection::vec(any::<[u8; 32]>(), 0..10),
+        ) {
+            check_update_leaf_matches_rebuilt_tree::<5>(leaves, updates, appended_leaves)?;
+        }
+
+        #[test]
+        fn test_insert_leaves_matches_insert_leaf_in_binary_tree(
+            batches in prop::collection::vec(prop::collection::vec(any::<[u8; 32]>(), 0..8), 1..5),
+        ) {
+            check_insert_leaves_matches_insert_leaf::<2>(batches)?;
+        }
+
+        #[test]
+        fn test_insert_leaves_matches_insert_leaf_in_quinary_tree(
+            batches in prop::collection::vec(prop::collection::vec(any::<[u8; 32]>(), 0..12), 1..5),
+        ) {
+            check_insert_leaves_matches_insert_leaf::<5>(batches)?;
+        }
     }
 }
@@ -1,16 +1,49 @@
-use maki_shared::types::SerializedProof;
-use plonk_prover::{verifyProcessMessage, verifyVoteTally};
+use maki_shared::types::{PoseidonHash, PublicKey, SerializedProof, TreeRoot};
+use plonk_prover::{verify_process_message, verify_vote_tally};
 
-pub fn verify_proof_process_message(proof: &SerializedProof, pp: &[u8]) -> bool {
-    // TODO
-    verifyProcessMessage(pp, proof, f)
-        .map(|_| true)
-        .unwrap_or(false)
+use crate::maki_types::PollId;
+
+#[allow(clippy::too_many_arguments)]
+pub fn verify_proof_process_message(
+    proof: &SerializedProof,
+    verifier_key: &[u8],
+    message_root: TreeRoot,
+    current_state_root: TreeRoot,
+    new_state_root: TreeRoot,
+    coordinator_public_key: PublicKey,
+    batch_start_index: u32,
+    batch_end_index: u32,
+    poll_id: PollId,
+) -> bool {
+    verify_process_message(
+        verifier_key,
+        message_root,
+        current_state_root,
+        new_state_root,
+        coordinator_public_key,
+        batch_start_index,
+        batch_end_index,
+        poll_id,
+        proof,
+    )
+    .is_ok()
 }
 
-pub fn verify_proof_vote_tally(proof: &SerializedProof, pp: &[u8]) -> bool {
-    // TODO
-    verifyVoteTally(pp, proof, f)
-        .map(|_| true)
-        .unwrap_or(false)
+pub fn verify_proof_vote_tally(
+    proof: &SerializedProof,
+    verifier_key: &[u8],
+    state_root: TreeRoot,
+    batch_start_index: u32,
+    current_tally_commitment: PoseidonHash,
+    new_tally_commitment: PoseidonHash,
+) -> bool {
+    verify_vote_tally(
+        verifier_key,
+        state_root,
+        batch_start_index,
+        current_tally_commitment,
+        new_tally_commitment,
+        proof,
+    )
+    .is_ok()
 }
@@ -1,91 +1,1402 @@
+use blake2::{Blake2b512, Digest};
+use dusk_jubjub::{JubJubAffine, JubJubScalar, GENERATOR};
 use dusk_plonk::prelude::*;
+use dusk_poseidon::sponge;
+use maki_shared::command::{
+    COMMAND_FIELD_BITS, MESSAGE_CIPHERTEXT_LENGTH, MESSAGE_DATA_MAX_LENGTH,
+    MESSAGE_ENCRYPTION_NONCE, MESSAGE_PLAINTEXT_LENGTH, PACKED_COMMAND_LENGTH, PACKED_FIELDS,
+    VOTE_MESSAGE_TYPE,
+};
+use maki_shared::functions_utils::u128_to_scalar;
+use maki_shared::types::PublicKey;
+
+use crate::gadgets::*;
 
 pub(crate) const LABEL_TRANSCRIPT: &[u8; 14] = b"maki-arguments";
 
+/// Number of messages processed by a proof, a batch of the poll can not hold more messages.
+pub const MESSAGE_BATCH_SIZE: usize = 4;
+
+/// Depth of the quinary state tree.
+pub const STATE_TREE_DEPTH: usize = 10;
+
+/// Depth of the binary message tree.
+pub const MESSAGE_TREE_DEPTH: usize = 10;
+
+/// Depth of the quinary vote option trees, the ballots of the users.
+pub const VOTE_OPTION_TREE_DEPTH: usize = 2;
+
+/// Depth of the subtrees of the state tree whose ballots are tallied by a proof.
+pub const TALLY_BATCH_DEPTH: usize = 1;
+
+/// Number of state leaves whose ballots are tallied by a proof.
+pub const TALLY_BATCH_SIZE: usize = STATE_TREE_ARITY.pow(TALLY_BATCH_DEPTH as u32);
+
+/// Number of vote options of a poll, the leaves of the vote option trees.
+pub const NUMBER_OF_VOTE_OPTIONS: usize = VOTE_OPTION_TREE_ARITY.pow(VOTE_OPTION_TREE_DEPTH as u32);
+
+const STATE_TREE_ARITY: usize = 5;
+const MESSAGE_TREE_ARITY: usize = 2;
+const VOTE_OPTION_TREE_ARITY: usize = 5;
+
+/// A user's state leaf, when it is not empty.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StateLeafOpening {
+    pub public_key: PublicKey,
+    pub voice_credit_balance: u128,
+    /// Zero until the user's first vote, the ballot is then empty
+    pub vote_option_tree_root: BlsScalar,
+    pub nonce: BlsScalar,
+}
+
+/// Private inputs of the processing of one message of a batch.
+///
+/// The state leaf and the ballot are opened as left by the previous messages of the batch, at the state index
+/// and the vote option index of the command. They are opened at index zero if the message is not a vote, can not
+/// be decrypted and unpacked, or if the index is out of the tree. Paths hold the siblings of each level, from the leaves.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessedMessage {
+    pub message_type: u8,
+    pub message_data: Vec<BlsScalar>,
+    pub ecdh_public_key: PublicKey,
+    pub message_path: Vec<Vec<BlsScalar>>,
+    /// `None` if the leaf is empty
+    pub state_leaf: Option<StateLeafOpening>,
+    pub state_path: Vec<Vec<BlsScalar>>,
+    /// Current weight of the vote for the opened vote option
+    pub vote_weight: u64,
+    pub ballot_path: Vec<Vec<BlsScalar>>,
+}
+
+impl Default for ProcessedMessage {
+    fn default() -> Self {
+        Self {
+            message_type: 0,
+            message_data: Vec::new(),
+            ecdh_public_key: PublicKey::from(GENERATOR),
+            message_path: Vec::new(),
+            state_leaf: None,
+            state_path: Vec::new(),
+            vote_weight: 0,
+            ballot_path: Vec::new(),
+        }
+    }
+}
+
+/// A state leaf of a tallied batch and the vote weights of its ballot, by vote option.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TalliedStateLeaf {
+    /// `None` if the leaf is empty
+    pub state_leaf: Option<StateLeafOpening>,
+    /// Missing vote weights are zeros
+    pub ballot: Vec<u64>,
+}
+
+/// Results of the vote options and total voice credits spent by the users, committed to with a salt.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Tally {
+    pub results: Vec<u128>,
+    pub total_spent_voice_credits: u128,
+    pub salt: BlsScalar,
+}
+
+impl Tally {
+    /// Poseidon hash of the results, the total spent voice credits and the salt.
+    /// `results` must hold the result of each vote option.
+    pub fn commitment(&self) -> BlsScalar {
+        let mut elements: Vec<BlsScalar> = self
+            .results
+            .iter()
+            .map(|result| u128_to_scalar(*result))
+            .collect();
+        elements.extend_from_slice(&[u128_to_scalar(self.total_spent_voice_credits), self.salt]);
+
+        sponge::hash(&elements)
+    }
+}
+
+/// Proves that processing the messages of a batch, in order, turns the current state root into the new one.
+///
+/// Each message is decrypted with the ECDH shared key of the coordinator and the message's ephemeral key. The
+/// command is applied if its signature was made with the key of its state leaf, its nonce follows the leaf's
+/// nonce, it targets the poll, its indices are in the trees and the user has enough voice credits for the
+/// quadratic cost of the vote. Any other message, including a message of another type than a vote, leaves the
+/// state unchanged.
 #[derive(Debug, Default)]
-pub(crate) struct MakiVoteTallyCircuit {
+pub(crate) struct MakiProcessMessageCircuit<
+    const BATCH_SIZE: usize = MESSAGE_BATCH_SIZE,
+    const STATE_DEPTH: usize = STATE_TREE_DEPTH,
+    const MESSAGE_DEPTH: usize = MESSAGE_TREE_DEPTH,
+    const VOTE_OPTION_DEPTH: usize = VOTE_OPTION_TREE_DEPTH,
+> {
     // private inputs
-    pub a: BlsScalar,
-    pub b: BlsScalar,
-    pub c: BlsScalar,
-    pub d: BlsScalar,
-    pub hashed_private_key: BlsScalar,
-    pub vote_result: BlsScalar,
+    pub coordinator_private_key: JubJubScalar,
+    pub messages: Vec<ProcessedMessage>,
     // public inputs
+    pub message_root: BlsScalar,
+    pub current_state_root: BlsScalar,
     pub new_state_root: BlsScalar,
-    pub public_key: BlsScalar,
+    pub coordinator_public_key_x: BlsScalar,
+    pub coordinator_public_key_y: BlsScalar,
+    pub batch_start_index: BlsScalar,
+    pub batch_end_index: BlsScalar,
+    pub poll_id: BlsScalar,
+}
+
+/// Witnesses shared by the messages of a batch.
+#[derive(Clone, Copy)]
+struct BatchWitnesses {
+    message_root: Witness,
+    batch_start_index: Witness,
+    poll_id: Witness,
+    coordinator_private_key_bits: [Witness; JUBJUB_SCALAR_BITS],
+    empty_state_leaf: Witness,
+    empty_ballot_root: Witness,
 }
-// TODO : change checks on circuit and inputs (both public and private)
-// Implement a circuit that checks:
-// 1) a + b = c where C is a PI
-// 2) a <= 2^6
-// 3) b <= 2^5
-// 4) a * b = d where D is a PI
-// TODO
 
-impl Circuit for MakiVoteTallyCircuit {
+impl<
+        const BATCH_SIZE: usize,
+        const STATE_DEPTH: usize,
+        const MESSAGE_DEPTH: usize,
+        const VOTE_OPTION_DEPTH: usize,
+    > Circuit
+    for MakiProcessMessageCircuit<BATCH_SIZE, STATE_DEPTH, MESSAGE_DEPTH, VOTE_OPTION_DEPTH>
+{
     fn circuit<C>(&self, composer: &mut C) -> Result<(), Error>
     where
         C: Composer,
     {
-        let hashed_private_key = composer.append_witness(self.hashed_private_key);
-        let vote_result = composer.append_witness(self.vote_result);
+        let message_root = composer.append_public(self.message_root);
+        let current_state_root = composer.append_public(self.current_state_root);
+        let new_state_root = composer.append_public(self.new_state_root);
+        let coordinator_public_key_x = composer.append_public(self.coordinator_public_key_x);
+        let coordinator_public_key_y = composer.append_public(self.coordinator_public_key_y);
+        let batch_start_index = composer.append_public(self.batch_start_index);
+        let batch_end_index = composer.append_public(self.batch_end_index);
+        let poll_id = composer.append_public(self.poll_id);
 
-        let a = composer.append_witness(self.a);
-        let b = composer.append_witness(self.b);
+        // The coordinator's private key matches its public key
+        let coordinator_private_key =
+            composer.append_witness(BlsScalar::from(self.coordinator_private_key));
+        let coordinator_private_key_bits =
+            composer.component_decomposition::<JUBJUB_SCALAR_BITS>(coordinator_private_key);
 
-        // Make first constraint a + b = c
-        let constraint = Constraint::new().left(1).right(1).public(-self.c).a(a).b(b);
+        let generator = composer.append_constant_point(GENERATOR);
+        let coordinator_public_key =
+            mul_point_bits(composer, &coordinator_private_key_bits, generator);
 
-        composer.append_gate(constraint);
+        composer.assert_equal(*coordinator_public_key.x(), coordinator_public_key_x);
+        composer.assert_equal(*coordinator_public_key.y(), coordinator_public_key_y);
 
-        // Check that a and b are in range
-        composer.component_range(a, 1 << 6);
-        composer.component_range(b, 1 << 5);
+        let batch = BatchWitnesses {
+            message_root,
+            batch_start_index,
+            poll_id,
+            coordinator_private_key_bits,
+            empty_state_leaf: composer.append_constant(empty_leaf()),
+            empty_ballot_root: composer.append_constant(empty_ballot_root(VOTE_OPTION_DEPTH)),
+        };
 
-        // Make second constraint a * b = d
-        let constraint = Constraint::new().mult(1).public(-self.d).a(a).b(b);
+        let batch_length = sub(composer, batch_end_index, batch_start_index);
+        let active_slots = active_slots::<C, BATCH_SIZE>(composer, batch_length);
 
-        composer.append_gate(constraint);
+        let default_message = ProcessedMessage::default();
 
-        // let e = composer.append_witness(self.e);
-        // let scalar_mul_result =
-        //     composer.component_mul_generator(e, dusk_jubjub::GENERATOR_EXTENDED)?;
+        let state_root = active_slots.iter().enumerate().fold(
+            current_state_root,
+            |state_root, (slot, active)| {
+                let message = self.messages.get(slot).unwrap_or(&default_message);
 
-        // // Apply the constraint
-        // composer.assert_equal_public_point(scalar_mul_result, self.f);
+                process_message::<C, STATE_DEPTH, MESSAGE_DEPTH, VOTE_OPTION_DEPTH>(
+                    composer, &batch, message, slot, *active, state_root,
+                )
+            },
+        );
 
-        let new_state_root = composer.append_public(self.new_state_root);
-        let public_key = composer.append_public(self.public_key);
+        composer.assert_equal(state_root, new_state_root);
 
         Ok(())
     }
 }
 
+/// Boolean witnesses, one for the slots of the batch holding a message.
+fn active_slots<C: Composer, const BATCH_SIZE: usize>(
+    composer: &mut C,
+    batch_length: Witness,
+) -> [Witness; BATCH_SIZE] {
+    // One-hot encoding of the batch length, which can not exceed the batch size
+    let length = composer[batch_length];
+    let lengths: Vec<Witness> = (0..=BATCH_SIZE)
+        .map(|i| {
+            let is_length = BlsScalar::from(i as u64) == length;
+            composer.append_witness(BlsScalar::from(u64::from(is_length)))
+        })
+        .collect();
+
+    let mut set_bits = C::ZERO;
+    let mut recomposed = C::ZERO;
+    for (i, bit) in lengths.iter().enumerate() {
+        composer.component_boolean(*bit);
+
+        set_bits = add(composer, set_bits, *bit);
+
+        let constraint = Constraint::new()
+            .left(1)
+            .right(BlsScalar::from(i as u64))
+            .a(recomposed)
+            .b(*bit);
+        recomposed = composer.gate_add(constraint);
+    }
+    composer.assert_equal_constant(set_bits, BlsScalar::one(), None);
+    composer.assert_equal(recomposed, batch_length);
+
+    // A slot is active if the length is greater than its index
+    core::array::from_fn(|slot| {
+        lengths[slot + 1..]
+            .iter()
+            .fold(C::ZERO, |active, bit| add(composer, active, *bit))
+    })
+}
+
+/// Applies the message to the state if its command is valid, returns the new state root.
+fn process_message<
+    C: Composer,
+    const STATE_DEPTH: usize,
+    const MESSAGE_DEPTH: usize,
+    const VOTE_OPTION_DEPTH: usize,
+>(
+    composer: &mut C,
+    batch: &BatchWitnesses,
+    message: &ProcessedMessage,
+    slot: usize,
+    active: Witness,
+    state_root: Witness,
+) -> Witness {
+    // The message is in the message tree, at its index
+    let message_type = composer.append_witness(BlsScalar::from(u64::from(message.message_type)));
+    let data_length = composer.append_witness(BlsScalar::from(message.message_data.len() as u64));
+    let data: Vec<Witness> = (0..MESSAGE_DATA_MAX_LENGTH)
+        .map(|i| {
+            let element = message.message_data.get(i).copied();
+            composer.append_witness(element.unwrap_or_else(BlsScalar::zero))
+        })
+        .collect();
+    let ecdh_public_key = composer.append_point(JubJubAffine::from_raw_unchecked(
+        message.ecdh_public_key.x,
+        message.ecdh_public_key.y,
+    ));
+
+    let mut message_elements = vec![message_type, data_length];
+    message_elements.extend_from_slice(&data);
+    message_elements.extend_from_slice(&[*ecdh_public_key.x(), *ecdh_public_key.y()]);
+    let message_leaf = sponge::gadget(composer, &message_elements);
+
+    let constraint = Constraint::new()
+        .left(1)
+        .constant(BlsScalar::from(slot as u64))
+        .a(batch.batch_start_index);
+    let message_index = composer.gate_add(constraint);
+    let message_index = composer.component_select_zero(active, message_index);
+
+    let message_positions =
+        tree_positions::<C, MESSAGE_TREE_ARITY>(composer, message_index, MESSAGE_DEPTH);
+    let message_siblings =
+        append_siblings::<C, MESSAGE_TREE_ARITY>(composer, &message.message_path, MESSAGE_DEPTH);
+    let message_root = merkle_root(
+        composer,
+        message_leaf,
+        &message_positions,
+        &message_siblings,
+    );
+
+    assert_equal_if(composer, active, message_root, batch.message_root);
+
+    // Decryption with the ECDH shared key
+    let shared_key = derive_shared_key(
+        composer,
+        &batch.coordinator_private_key_bits,
+        ecdh_public_key,
+    );
+    let nonce = composer.append_constant(MESSAGE_ENCRYPTION_NONCE);
+    let (plaintext, is_decrypted) = poseidon_decrypt(
+        composer,
+        &data[..MESSAGE_CIPHERTEXT_LENGTH],
+        MESSAGE_PLAINTEXT_LENGTH,
+        shared_key,
+        nonce,
+    );
+    let has_command_length = is_equal_constant(
+        composer,
+        data_length,
+        BlsScalar::from(MESSAGE_CIPHERTEXT_LENGTH as u64),
+    );
+    let is_vote = is_equal_constant(
+        composer,
+        message_type,
+        BlsScalar::from(u64::from(VOTE_MESSAGE_TYPE)),
+    );
+
+    // Unpacking of the command
+    let field_bits = COMMAND_FIELD_BITS as usize;
+    let packed_bits = canonical_bits(composer, plaintext[0]);
+    let unused_bits = from_bits(
+        composer,
+        &packed_bits[PACKED_FIELDS as usize * field_bits..],
+    );
+    let is_packed = is_zero(composer, unused_bits);
+
+    let field = |i: usize| &packed_bits[i * field_bits..(i + 1) * field_bits];
+    let (nonce_bits, state_index_bits, vote_option_index_bits, new_vote_weight_bits, poll_id_bits) =
+        (field(0), field(1), field(2), field(3), field(4));
+
+    let command_nonce = from_bits(composer, nonce_bits);
+    let state_index = from_bits(composer, state_index_bits);
+    let vote_option_index = from_bits(composer, vote_option_index_bits);
+    let new_vote_weight = from_bits(composer, new_vote_weight_bits);
+    let command_poll_id = from_bits(composer, poll_id_bits);
+
+    let new_public_key_x = plaintext[1];
+    let new_public_key_y = plaintext[2];
+    let command_hash = sponge::gadget(composer, &plaintext[..PACKED_COMMAND_LENGTH]);
+
+    let is_unpacked = and_all(
+        composer,
+        &[active, is_vote, has_command_length, is_decrypted, is_packed],
+    );
+
+    // The state leaf is in the state tree
+    let state_tree_size =
+        BlsScalar::from(STATE_TREE_ARITY as u64).pow(&[STATE_DEPTH as u64, 0, 0, 0]);
+    let is_state_index_valid =
+        is_less_than_constant(composer, state_index_bits, &state_tree_size, false);
+    let opens_state_index = and(composer, is_unpacked, is_state_index_valid);
+    let state_index = composer.component_select_zero(opens_state_index, state_index);
+
+    let state_positions = tree_positions::<C, STATE_TREE_ARITY>(composer, state_index, STATE_DEPTH);
+    let state_siblings =
+        append_siblings::<C, STATE_TREE_ARITY>(composer, &message.state_path, STATE_DEPTH);
+
+    // The key of an empty leaf is a valid point so that the signature can still be checked
+    let opening = message.state_leaf.unwrap_or(StateLeafOpening {
+        public_key: PublicKey::from(GENERATOR),
+        voice_credit_balance: 0,
+        vote_option_tree_root: BlsScalar::zero(),
+        nonce: BlsScalar::zero(),
+    });
+    let is_empty =
+        composer.append_witness(BlsScalar::from(u64::from(message.state_leaf.is_none())));
+    composer.component_boolean(is_empty);
+
+    let public_key = composer.append_point(JubJubAffine::from_raw_unchecked(
+        opening.public_key.x,
+        opening.public_key.y,
+    ));
+    let voice_credit_balance =
+        composer.append_witness(u128_to_scalar(opening.voice_credit_balance));
+    let vote_option_tree_root = composer.append_witness(opening.vote_option_tree_root);
+    let state_nonce = composer.append_witness(opening.nonce);
+
+    let leaf_hash = sponge::gadget(
+        composer,
+        &[
+            *public_key.x(),
+            *public_key.y(),
+            voice_credit_balance,
+            vote_option_tree_root,
+            state_nonce,
+        ],
+    );
+    let state_leaf = composer.component_select(is_empty, batch.empty_state_leaf, leaf_hash);
+    let opened_state_root = merkle_root(composer, state_leaf, &state_positions, &state_siblings);
+
+    assert_equal_if(composer, active, opened_state_root, state_root);
+
+    let is_not_empty = not(composer, is_empty);
+
+    // The command is signed with the key of the state leaf
+    let is_signature_valid = verify_signature(
+        composer,
+        public_key,
+        command_hash,
+        plaintext[4],
+        plaintext[5],
+        plaintext[6],
+    );
+
+    let (is_new_public_key_valid, _) =
+        is_prime_order_point(composer, new_public_key_x, new_public_key_y);
+
+    let constraint = Constraint::new().left(1).constant(1).a(state_nonce);
+    let expected_nonce = composer.gate_add(constraint);
+    let is_nonce_valid = is_equal(composer, command_nonce, expected_nonce);
+
+    let is_poll_id_valid = is_equal(composer, command_poll_id, batch.poll_id);
+
+    // The current vote weight is in the user's ballot, a zero root being an empty ballot
+    let vote_option_tree_size =
+        BlsScalar::from(VOTE_OPTION_TREE_ARITY as u64).pow(&[VOTE_OPTION_DEPTH as u64, 0, 0, 0]);
+    let is_vote_option_index_valid = is_less_than_constant(
+        composer,
+        vote_option_index_bits,
+        &vote_option_tree_size,
+        false,
+    );
+    let opens_vote_option_index = and(composer, is_unpacked, is_vote_option_index_valid);
+    let vote_option_index =
+        composer.component_select_zero(opens_vote_option_index, vote_option_index);
+
+    let ballot_positions =
+        tree_positions::<C, VOTE_OPTION_TREE_ARITY>(composer, vote_option_index, VOTE_OPTION_DEPTH);
+    let ballot_siblings = append_siblings::<C, VOTE_OPTION_TREE_ARITY>(
+        composer,
+        &message.ballot_path,
+        VOTE_OPTION_DEPTH,
+    );
+    let vote_weight = composer.append_witness(BlsScalar::from(message.vote_weight));
+    let opened_ballot_root =
+        merkle_root(composer, vote_weight, &ballot_positions, &ballot_siblings);
+
+    let has_empty_ballot = is_zero(composer, vote_option_tree_root);
+    let ballot_root = composer.component_select(
+        has_empty_ballot,
+        batch.empty_ballot_root,
+        vote_option_tree_root,
+    );
+    let opens_ballot = and(composer, active, is_not_empty);
+
+    assert_equal_if(composer, opens_ballot, opened_ballot_root, ballot_root);
+
+    // The voice credits cover the quadratic cost of the new vote, once the current vote is refunded
+    let current_cost = mul(composer, vote_weight, vote_weight);
+    let new_cost = mul(composer, new_vote_weight, new_vote_weight);
+    let constraint = Constraint::new()
+        .left(1)
+        .right(1)
+        .fourth(-BlsScalar::one())
+        .a(voice_credit_balance)
+        .b(current_cost)
+        .d(new_cost);
+    let new_voice_credit_balance = composer.gate_add(constraint);
+
+    // Balances fit in 129 bits and costs in 100 bits, the shifted balance is thus lower than 2^131
+    // and at least 2^130 if and only if the new balance is not negative
+    let constraint = Constraint::new()
+        .left(1)
+        .constant(BlsScalar::pow_of_2(130))
+        .a(new_voice_credit_balance);
+    let shifted_balance = composer.gate_add(constraint);
+    let shifted_balance_bits = composer.component_decomposition::<131>(shifted_balance);
+    let has_enough_voice_credits = shifted_balance_bits[130];
+
+    let is_valid = and_all(
+        composer,
+        &[
+            is_unpacked,
+            is_state_index_valid,
+            is_not_empty,
+            is_signature_valid,
+            is_new_public_key_valid,
+            is_nonce_valid,
+            is_poll_id_valid,
+            is_vote_option_index_valid,
+            has_enough_voice_credits,
+        ],
+    );
+
+    // The state leaf is updated with the command if it is valid
+    let new_ballot_root = merkle_root(
+        composer,
+        new_vote_weight,
+        &ballot_positions,
+        &ballot_siblings,
+    );
+    let new_leaf_hash = sponge::gadget(
+        composer,
+        &[
+            new_public_key_x,
+            new_public_key_y,
+            new_voice_credit_balance,
+            new_ballot_root,
+            command_nonce,
+        ],
+    );
+    let new_state_leaf = composer.component_select(is_valid, new_leaf_hash, state_leaf);
+    let new_state_root = merkle_root(composer, new_state_leaf, &state_positions, &state_siblings);
+
+    composer.component_select(active, new_state_root, state_root)
+}
+
+/// Proves that tallying the ballots of a batch of state leaves turns the current tally into the new one.
+///
+/// The batch is a complete subtree of the state tree, starting at `batch_start_index`. The vote weights of each
+/// ballot are added to the results of their vote options and their squares to the total spent voice credits.
+/// Tallies are published as salted commitments, the current tally of the first batch is empty.
 #[derive(Debug, Default)]
-pub(crate) struct MakiProcessMessageCircuit {
+pub(crate) struct MakiVoteTallyCircuit<
+    const BATCH_DEPTH: usize = TALLY_BATCH_DEPTH,
+    const STATE_DEPTH: usize = STATE_TREE_DEPTH,
+    const VOTE_OPTION_DEPTH: usize = VOTE_OPTION_TREE_DEPTH,
+> {
     // private inputs
-    pub hashed_private_key: BlsScalar,
-    pub ecdh_private_key: BlsScalar,
+    pub state_leaves: Vec<TalliedStateLeaf>,
+    pub state_path: Vec<Vec<BlsScalar>>,
+    pub current_tally: Tally,
+    pub new_salt: BlsScalar,
     // public inputs
-    pub new_state_root: BlsScalar,
-    pub public_key: BlsScalar,
-    pub ecdh_public_key: BlsScalar,
+    pub state_root: BlsScalar,
+    pub batch_start_index: BlsScalar,
+    pub current_tally_commitment: BlsScalar,
+    pub new_tally_commitment: BlsScalar,
 }
-// TODO : change checks on circuit and inputs (both public and private)
 
-impl Circuit for MakiProcessMessageCircuit {
+impl<const BATCH_DEPTH: usize, const STATE_DEPTH: usize, const VOTE_OPTION_DEPTH: usize> Circuit
+    for MakiVoteTallyCircuit<BATCH_DEPTH, STATE_DEPTH, VOTE_OPTION_DEPTH>
+{
     fn circuit<C>(&self, composer: &mut C) -> Result<(), Error>
     where
         C: Composer,
     {
-        let hashed_private_key = composer.append_witness(self.hashed_private_key);
-        let ecdh_private_key = composer.append_witness(self.ecdh_private_key);
+        let state_root = composer.append_public(self.state_root);
+        let batch_start_index = composer.append_public(self.batch_start_index);
+        let current_tally_commitment = composer.append_public(self.current_tally_commitment);
+        let new_tally_commitment = composer.append_public(self.new_tally_commitment);
 
-        let new_state_root = composer.append_public(self.new_state_root);
-        let public_key = composer.append_public(self.public_key);
-        let ecdh_public_key = composer.append_public(self.ecdh_public_key);
+        let batch_size = STATE_TREE_ARITY.pow(BATCH_DEPTH as u32);
+        let vote_options = VOTE_OPTION_TREE_ARITY.pow(VOTE_OPTION_DEPTH as u32);
+
+        // The batch starts at a subtree of the state tree
+        let batch_start = composer[batch_start_index].reduce().0[0];
+        let batch_index = composer.append_witness(BlsScalar::from(batch_start / batch_size as u64));
+        let constraint = Constraint::new()
+            .left(BlsScalar::from(batch_size as u64))
+            .a(batch_index);
+        let batch_index_start = composer.gate_add(constraint);
+        composer.assert_equal(batch_index_start, batch_start_index);
+
+        let batch_positions =
+            tree_positions::<C, STATE_TREE_ARITY>(composer, batch_index, STATE_DEPTH - BATCH_DEPTH);
+        let batch_siblings = append_siblings::<C, STATE_TREE_ARITY>(
+            composer,
+            &self.state_path,
+            STATE_DEPTH - BATCH_DEPTH,
+        );
+
+        // The current tally is committed to, unless the batch is the first one
+        let current_results: Vec<Witness> = (0..vote_options)
+            .map(|i| {
+                let result = self.current_tally.results.get(i).copied().unwrap_or(0);
+                composer.append_witness(u128_to_scalar(result))
+            })
+            .collect();
+        let current_total_spent_voice_credits =
+            composer.append_witness(u128_to_scalar(self.current_tally.total_spent_voice_credits));
+        let current_salt = composer.append_witness(self.current_tally.salt);
+
+        let mut current_tally = current_results.clone();
+        current_tally.extend_from_slice(&[current_total_spent_voice_credits, current_salt]);
+        let computed_current_tally_commitment = sponge::gadget(composer, &current_tally);
+
+        let is_first_batch = is_zero(composer, batch_start_index);
+        let is_not_first_batch = not(composer, is_first_batch);
+        let expected_current_tally_commitment =
+            composer.component_select_zero(is_not_first_batch, computed_current_tally_commitment);
+        composer.assert_equal(expected_current_tally_commitment, current_tally_commitment);
+
+        let mut results: Vec<Witness> = current_results
+            .iter()
+            .map(|result| composer.component_select_zero(is_not_first_batch, *result))
+            .collect();
+        let mut total_spent_voice_credits =
+            composer.component_select_zero(is_not_first_batch, current_total_spent_voice_credits);
+
+        // The ballot of each state leaf is tallied
+        let empty_state_leaf = composer.append_constant(empty_leaf());
+        let empty_ballot_root = composer.append_constant(empty_ballot_root(VOTE_OPTION_DEPTH));
+        let default_state_leaf = TalliedStateLeaf::default();
+
+        let mut nodes: Vec<Witness> = (0..batch_size)
+            .map(|i| {
+                let tallied_state_leaf = self.state_leaves.get(i).unwrap_or(&default_state_leaf);
+                let opening = tallied_state_leaf.state_leaf.unwrap_or(StateLeafOpening {
+                    public_key: PublicKey::from(GENERATOR),
+                    voice_credit_balance: 0,
+                    vote_option_tree_root: BlsScalar::zero(),
+                    nonce: BlsScalar::zero(),
+                });
+
+                let (state_leaf, vote_weights) = tally_ballot::<C, VOTE_OPTION_DEPTH>(
+                    composer,
+                    tallied_state_leaf.state_leaf.is_none(),
+                    &opening,
+                    &tallied_state_leaf.ballot,
+                    empty_state_leaf,
+                    empty_ballot_root,
+                );
+
+                for (result, vote_weight) in results.iter_mut().zip(vote_weights.iter()) {
+                    *result = add(composer, *result, *vote_weight);
+
+                    let constraint = Constraint::new()
+                        .mult(1)
+                        .fourth(1)
+                        .a(*vote_weight)
+                        .b(*vote_weight)
+                        .d(total_spent_voice_credits);
+                    total_spent_voice_credits = composer.gate_mul(constraint);
+                }
+
+                state_leaf
+            })
+            .collect();
+
+        // The batch is in the state tree
+        while nodes.len() > 1 {
+            nodes = nodes
+                .chunks(STATE_TREE_ARITY)
+                .map(|children| sponge::gadget(composer, children))
+                .collect();
+        }
+        let batch_state_root = merkle_root(composer, nodes[0], &batch_positions, &batch_siblings);
+
+        composer.assert_equal(batch_state_root, state_root);
+
+        // The new tally is committed to with the new salt
+        let new_salt = composer.append_witness(self.new_salt);
+
+        let mut new_tally = results;
+        new_tally.extend_from_slice(&[total_spent_voice_credits, new_salt]);
+        let computed_new_tally_commitment = sponge::gadget(composer, &new_tally);
+
+        composer.assert_equal(computed_new_tally_commitment, new_tally_commitment);
 
         Ok(())
     }
 }
+
+/// Returns the hash of the state leaf and the vote weights of its ballot, which are zeros if the leaf is empty
+/// or the ballot has no votes. The opening of an empty leaf is not hashed, its vote option tree root must be zero.
+fn tally_ballot<C: Composer, const VOTE_OPTION_DEPTH: usize>(
+    composer: &mut C,
+    is_empty: bool,
+    opening: &StateLeafOpening,
+    ballot: &[u64],
+    empty_state_leaf: Witness,
+    empty_ballot_root: Witness,
+) -> (Witness, Vec<Witness>) {
+    let vote_options = VOTE_OPTION_TREE_ARITY.pow(VOTE_OPTION_DEPTH as u32);
+
+    let is_empty = composer.append_witness(BlsScalar::from(u64::from(is_empty)));
+    composer.component_boolean(is_empty);
+
+    let public_key_x = composer.append_witness(opening.public_key.x);
+    let public_key_y = composer.append_witness(opening.public_key.y);
+    let voice_credit_balance =
+        composer.append_witness(u128_to_scalar(opening.voice_credit_balance));
+    let vote_option_tree_root = composer.append_witness(opening.vote_option_tree_root);
+    let nonce = composer.append_witness(opening.nonce);
+
+    let leaf_hash = sponge::gadget(
+        composer,
+        &[
+            public_key_x,
+            public_key_y,
+            voice_credit_balance,
+            vote_option_tree_root,
+            nonce,
+        ],
+    );
+    let state_leaf = composer.component_select(is_empty, empty_state_leaf, leaf_hash);
+
+    // The vote weights are the leaves of the ballot, a zero root being an empty ballot
+    let vote_weights: Vec<Witness> = (0..vote_options)
+        .map(|i| {
+            let vote_weight = ballot.get(i).copied().unwrap_or(0);
+            composer.append_witness(BlsScalar::from(vote_weight))
+        })
+        .collect();
+
+    let mut nodes = vote_weights.clone();
+    while nodes.len() > 1 {
+        nodes = nodes
+            .chunks(VOTE_OPTION_TREE_ARITY)
+            .map(|children| sponge::gadget(composer, children))
+            .collect();
+    }
+
+    // An empty leaf has a zero root, its ballot is thus empty as well
+    assert_equal_if(composer, is_empty, vote_option_tree_root, C::ZERO);
+    let has_empty_ballot = is_zero(composer, vote_option_tree_root);
+    let ballot_root =
+        composer.component_select(has_empty_ballot, empty_ballot_root, vote_option_tree_root);
+
+    composer.assert_equal(nodes[0], ballot_root);
+
+    (state_leaf, vote_weights)
+}
+
+/// Appends the siblings of a path, missing siblings are zeros.
+fn append_siblings<C: Composer, const ARITY: usize>(
+    composer: &mut C,
+    path: &[Vec<BlsScalar>],
+    depth: usize,
+) -> Vec<Vec<Witness>> {
+    (0..depth)
+        .map(|level| {
+            (0..ARITY - 1)
+                .map(|i| {
+                    let sibling = path.get(level).and_then(|siblings| siblings.get(i));
+                    composer.append_witness(sibling.copied().unwrap_or_else(BlsScalar::zero))
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Empty leaf of the Merkle trees of `maki`, the nothing-up-my-sleeve value `BLAKE2b-512("maki")` reduced in the scalar field.
+pub(crate) fn empty_leaf() -> BlsScalar {
+    BlsScalar::from_bytes_wide(&Blake2b512::digest(b"maki").into())
+}
+
+/// Root of a ballot without votes, a vote option tree of zero leaves.
+pub(crate) fn empty_ballot_root(depth: usize) -> BlsScalar {
+    (0..depth).fold(BlsScalar::zero(), |zero, _| {
+        sponge::hash(&[zero; VOTE_OPTION_TREE_ARITY])
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::OnceLock;
+
+    use maki_shared::command::Command;
+    use maki_shared::ecdh::derive_shared_key;
+    use maki_shared::eddsa::{Keypair, Signature};
+    use maki_shared::types::PrivateKey;
+    use rand_core::OsRng;
+
+    const TEST_POLL_ID: u64 = 3;
+
+    // Proving is slow, the test circuits process two messages and tally five state leaves in the smallest trees
+    type TestProcessMessageCircuit = MakiProcessMessageCircuit<2, 1, 1, 1>;
+    type TestVoteTallyCircuit = MakiVoteTallyCircuit<1, 2, 1>;
+
+    type Keys<C> = (Prover<C>, Verifier<C>);
+
+    fn public_parameters() -> &'static PublicParameters {
+        static PUBLIC_PARAMETERS: OnceLock<PublicParameters> = OnceLock::new();
+
+        PUBLIC_PARAMETERS.get_or_init(|| PublicParameters::setup(1 << 17, &mut OsRng).unwrap())
+    }
+
+    // Compiling a circuit is as slow as proving it, the keys are shared by the tests
+    fn process_message_keys() -> &'static Keys<TestProcessMessageCircuit> {
+        static KEYS: OnceLock<Keys<TestProcessMessageCircuit>> = OnceLock::new();
+
+        KEYS.get_or_init(|| Compiler::compile(public_parameters(), LABEL_TRANSCRIPT).unwrap())
+    }
+
+    fn vote_tally_keys() -> &'static Keys<TestVoteTallyCircuit> {
+        static KEYS: OnceLock<Keys<TestVoteTallyCircuit>> = OnceLock::new();
+
+        KEYS.get_or_init(|| Compiler::compile(public_parameters(), LABEL_TRANSCRIPT).unwrap())
+    }
+
+    /// Tallies the ballot of one state leaf, whose hash and vote weights are public.
+    #[derive(Debug, Default)]
+    struct BallotCircuit {
+        is_empty: bool,
+        state_leaf: Option<StateLeafOpening>,
+        ballot: Vec<u64>,
+        state_leaf_hash: BlsScalar,
+        vote_weights: Vec<BlsScalar>,
+    }
+
+    impl Circuit for BallotCircuit {
+        fn circuit<C>(&self, composer: &mut C) -> Result<(), Error>
+        where
+            C: Composer,
+        {
+            let state_leaf_hash = composer.append_public(self.state_leaf_hash);
+
+            let empty_state_leaf = composer.append_constant(empty_leaf());
+            let empty_ballot_root = composer.append_constant(empty_ballot_root(1));
+            let opening = self.state_leaf.unwrap_or(StateLeafOpening {
+                public_key: PublicKey::from(GENERATOR),
+                voice_credit_balance: 0,
+                vote_option_tree_root: BlsScalar::zero(),
+                nonce: BlsScalar::zero(),
+            });
+
+            let (state_leaf, vote_weights) = tally_ballot::<C, 1>(
+                composer,
+                self.is_empty,
+                &opening,
+                &self.ballot,
+                empty_state_leaf,
+                empty_ballot_root,
+            );
+            composer.assert_equal(state_leaf, state_leaf_hash);
+
+            for (i, vote_weight) in vote_weights.iter().enumerate() {
+                let expected = self.vote_weights.get(i).copied().unwrap_or_default();
+                let expected = composer.append_public(expected);
+                composer.assert_equal(*vote_weight, expected);
+            }
+
+            Ok(())
+        }
+    }
+
+    fn ballot_keys() -> &'static Keys<BallotCircuit> {
+        static KEYS: OnceLock<Keys<BallotCircuit>> = OnceLock::new();
+
+        KEYS.get_or_init(|| Compiler::compile(public_parameters(), LABEL_TRANSCRIPT).unwrap())
+    }
+
+    fn prove_and_verify<C: Circuit>(keys: &Keys<C>, circuit: &C) -> Result<(), Error> {
+        let (prover, verifier) = keys;
+        let (proof, public_inputs) = prover.prove(&mut OsRng, circuit)?;
+
+        verifier.verify(&proof, &public_inputs)
+    }
+
+    fn keypair(seed: u64) -> Keypair {
+        Keypair::from_private_key(PrivateKey::from(JubJubScalar::from(seed)))
+    }
+
+    /// Root and path of the leaf at `index` of a complete tree.
+    fn root_and_path(
+        leaves: &[BlsScalar],
+        arity: usize,
+        index: usize,
+    ) -> (BlsScalar, Vec<Vec<BlsScalar>>) {
+        let mut level = leaves.to_vec();
+        let mut index = index;
+        let mut path = Vec::new();
+
+        while level.len() > 1 {
+            let first_child = index - index % arity;
+            let siblings = (first_child..first_child + arity)
+                .filter(|child| *child != index)
+                .map(|child| level[child])
+                .collect();
+            path.push(siblings);
+
+            level = level.chunks(arity).map(sponge::hash).collect();
+            index /= arity;
+        }
+
+        (level[0], path)
+    }
+
+    fn state_leaf_hash(leaf: &Option<StateLeafOpening>) -> BlsScalar {
+        match leaf {
+            Some(leaf) => sponge::hash(&[
+                leaf.public_key.x,
+                leaf.public_key.y,
+                u128_to_scalar(leaf.voice_credit_balance),
+                leaf.vote_option_tree_root,
+                leaf.nonce,
+            ]),
+            None => empty_leaf(),
+        }
+    }
+
+    /// Native processing of the messages, mirroring the circuit.
+    struct TestState {
+        leaves: Vec<Option<StateLeafOpening>>,
+        ballots: Vec<Vec<u64>>,
+    }
+
+    impl TestState {
+        fn new(users: &[(Keypair, u128)]) -> Self {
+            let size = STATE_TREE_ARITY;
+            let mut leaves = vec![None; size];
+
+            for (leaf, (keypair, voice_credit_balance)) in leaves.iter_mut().zip(users.iter()) {
+                *leaf = Some(StateLeafOpening {
+                    public_key: PublicKey::from(keypair.public_key),
+                    voice_credit_balance: *voice_credit_balance,
+                    vote_option_tree_root: BlsScalar::zero(),
+                    nonce: BlsScalar::zero(),
+                });
+            }
+
+            Self {
+                leaves,
+                ballots: vec![vec![0; VOTE_OPTION_TREE_ARITY]; size],
+            }
+        }
+
+        fn state_leaves(&self) -> Vec<BlsScalar> {
+            self.leaves.iter().map(state_leaf_hash).collect()
+        }
+
+        fn root(&self) -> BlsScalar {
+            root_and_path(&self.state_leaves(), STATE_TREE_ARITY, 0).0
+        }
+
+        fn process(
+            &mut self,
+            message_type: u8,
+            message: &[BlsScalar],
+            ecdh_public_key: PublicKey,
+        ) -> ProcessedMessage {
+            let shared_key = derive_shared_key(
+                &coordinator().private_key.scalar(),
+                &ecdh_public_key.to_affine().unwrap(),
+            );
+            let decrypted = Command::decrypt(message, &shared_key)
+                .filter(|_| message_type == VOTE_MESSAGE_TYPE);
+
+            let state_index = decrypted
+                .map(|(command, _)| command.state_index as usize)
+                .filter(|index| *index < self.leaves.len())
+                .unwrap_or(0);
+            let vote_option_index = decrypted
+                .map(|(command, _)| command.vote_option_index as usize)
+                .filter(|index| *index < VOTE_OPTION_TREE_ARITY)
+                .unwrap_or(0);
+
+            let ballot: Vec<BlsScalar> = self.ballots[state_index]
+                .iter()
+                .map(|weight| BlsScalar::from(*weight))
+                .collect();
+
+            let processed_message = ProcessedMessage {
+                message_type,
+                message_data: message.to_vec(),
+                ecdh_public_key,
+                message_path: Vec::new(),
+                state_leaf: self.leaves[state_index],
+                state_path: root_and_path(&self.state_leaves(), STATE_TREE_ARITY, state_index).1,
+                vote_weight: self.ballots[state_index][vote_option_index],
+                ballot_path: root_and_path(&ballot, VOTE_OPTION_TREE_ARITY, vote_option_index).1,
+            };
+
+            if let (Some((command, signature)), Some(leaf)) = (decrypted, self.leaves[state_index])
+            {
+                let current_weight = self.ballots[state_index][vote_option_index];
+                let available = leaf.voice_credit_balance + u128::from(current_weight).pow(2);
+                let cost = u128::from(command.new_vote_weight).pow(2);
+
+                let is_valid = command.state_index == state_index as u64
+                    && command.vote_option_index == vote_option_index as u64
+                    && command.verify_signature(&leaf.public_key, &signature)
+                    && command.new_public_key.is_valid()
+                    && BlsScalar::from(command.nonce) == leaf.nonce + BlsScalar::one()
+                    && command.poll_id == TEST_POLL_ID
+                    && available >= cost;
+
+                if is_valid {
+                    self.ballots[state_index][vote_option_index] = command.new_vote_weight;
+                    let ballot: Vec<BlsScalar> = self.ballots[state_index]
+                        .iter()
+                        .map(|weight| BlsScalar::from(*weight))
+                        .collect();
+
+                    self.leaves[state_index] = Some(StateLeafOpening {
+                        public_key: command.new_public_key,
+                        voice_credit_balance: available - cost,
+                        vote_option_tree_root: root_and_path(&ballot, VOTE_OPTION_TREE_ARITY, 0).0,
+                        nonce: BlsScalar::from(command.nonce),
+                    });
+                }
+            }
+
+            processed_message
+        }
+    }
+
+    fn coordinator() -> Keypair {
+        keypair(1000)
+    }
+
+    /// Encrypted message of a command signed with `signing_keypair`, and its ephemeral public key.
+    fn message(
+        command: &Command,
+        signing_keypair: &Keypair,
+        ephemeral_seed: u64,
+    ) -> (Vec<BlsScalar>, PublicKey) {
+        let signature: Signature = command.sign(&signing_keypair.private_key.scalar()).unwrap();
+        let ephemeral_keypair = keypair(ephemeral_seed);
+        let shared_key = derive_shared_key(
+            &ephemeral_keypair.private_key.scalar(),
+            &coordinator().public_key,
+        );
+
+        (
+            command.encrypt(&signature, &shared_key).unwrap(),
+            PublicKey::from(ephemeral_keypair.public_key),
+        )
+    }
+
+    fn message_leaf(
+        message_type: u8,
+        data: &[BlsScalar],
+        ecdh_public_key: &PublicKey,
+    ) -> BlsScalar {
+        let mut elements = vec![
+            BlsScalar::from(u64::from(message_type)),
+            BlsScalar::from(data.len() as u64),
+        ];
+        elements.extend_from_slice(data);
+        elements.resize(MESSAGE_DATA_MAX_LENGTH + 2, BlsScalar::zero());
+        elements.extend_from_slice(&[ecdh_public_key.x, ecdh_public_key.y]);
+
+        sponge::hash(&elements)
+    }
+
+    /// Processes the vote messages natively and builds the circuit proving it.
+    fn process_messages(
+        state: &mut TestState,
+        messages: &[(Vec<BlsScalar>, PublicKey)],
+    ) -> TestProcessMessageCircuit {
+        let typed_messages: Vec<(u8, Vec<BlsScalar>, PublicKey)> = messages
+            .iter()
+            .map(|(data, ecdh_public_key)| (VOTE_MESSAGE_TYPE, data.clone(), *ecdh_public_key))
+            .collect();
+
+        process_typed_messages(state, &typed_messages)
+    }
+
+    /// Processes the messages of the given types natively and builds the circuit proving it.
+    fn process_typed_messages(
+        state: &mut TestState,
+        messages: &[(u8, Vec<BlsScalar>, PublicKey)],
+    ) -> TestProcessMessageCircuit {
+        let mut message_leaves: Vec<BlsScalar> = messages
+            .iter()
+            .map(|(message_type, data, ecdh_public_key)| {
+                message_leaf(*message_type, data, ecdh_public_key)
+            })
+            .collect();
+        message_leaves.resize(MESSAGE_TREE_ARITY, empty_leaf());
+
+        let current_state_root = state.root();
+        let processed_messages = messages
+            .iter()
+            .enumerate()
+            .map(|(index, (message_type, data, ecdh_public_key))| {
+                let mut processed_message = state.process(*message_type, data, *ecdh_public_key);
+                processed_message.message_path =
+                    root_and_path(&message_leaves, MESSAGE_TREE_ARITY, index).1;
+                processed_message
+            })
+            .collect();
+
+        TestProcessMessageCircuit {
+            coordinator_private_key: coordinator().private_key.scalar(),
+            messages: processed_messages,
+            message_root: root_and_path(&message_leaves, MESSAGE_TREE_ARITY, 0).0,
+            current_state_root,
+            new_state_root: state.root(),
+            coordinator_public_key_x: coordinator().public_key.get_x(),
+            coordinator_public_key_y: coordinator().public_key.get_y(),
+            batch_start_index: BlsScalar::zero(),
+            batch_end_index: BlsScalar::from(messages.len() as u64),
+            poll_id: BlsScalar::from(TEST_POLL_ID),
+        }
+    }
+
+    fn vote(
+        state_index: u64,
+        keypair: &Keypair,
+        vote_option_index: u64,
+        new_vote_weight: u64,
+        nonce: u64,
+    ) -> Command {
+        Command::new(
+            state_index,
+            PublicKey::from(keypair.public_key),
+            vote_option_index,
+            new_vote_weight,
+            nonce,
+            TEST_POLL_ID,
+            BlsScalar::one(),
+        )
+    }
+
+    #[test]
+    fn process_messages_applies_valid_commands_and_ignores_invalid_ones() {
+        let users = [(keypair(1), 100), (keypair(2), 100)];
+        let mut state = TestState::new(&users);
+        let initial_root = state.root();
+
+        let valid_vote = message(&vote(0, &users[0].0, 2, 9, 1), &users[0].0, 10);
+        // Signed by the first user for the second user's leaf
+        let forged_vote = message(&vote(1, &users[1].0, 3, 5, 1), &users[0].0, 11);
+
+        let circuit = process_messages(&mut state, &[valid_vote, forged_vote]);
+
+        assert_ne!(circuit.new_state_root, initial_root);
+        assert_eq!(state.leaves[1].unwrap().voice_credit_balance, 100);
+        assert_eq!(state.leaves[0].unwrap().voice_credit_balance, 19);
+        assert!(prove_and_verify(process_message_keys(), &circuit).is_ok());
+    }
+
+    #[test]
+    fn process_messages_with_invalid_command_applied_fails() {
+        let users = [(keypair(1), 100)];
+        let mut state = TestState::new(&users);
+
+        let valid_vote = message(&vote(0, &users[0].0, 2, 9, 1), &users[0].0, 10);
+        // Replays the nonce of the first vote and costs more voice credits than the user has
+        let replayed_vote = message(&vote(0, &users[0].0, 1, 11, 1), &users[0].0, 11);
+
+        let mut circuit = process_messages(&mut state, &[valid_vote, replayed_vote]);
+
+        let replayed_ballot = vec![0, 11, 9, 0, 0]
+            .into_iter()
+            .map(BlsScalar::from)
+            .collect::<Vec<_>>();
+        state.leaves[0] = Some(StateLeafOpening {
+            public_key: PublicKey::from(users[0].0.public_key),
+            voice_credit_balance: 0,
+            vote_option_tree_root: root_and_path(&replayed_ballot, VOTE_OPTION_TREE_ARITY, 0).0,
+            nonce: BlsScalar::one(),
+        });
+        circuit.new_state_root = state.root();
+
+        assert!(prove_and_verify(process_message_keys(), &circuit).is_err());
+    }
+
+    #[test]
+    fn process_messages_with_partial_batch_succeeds() {
+        let users = [(keypair(1), 100)];
+        let mut state = TestState::new(&users);
+        let new_keypair = keypair(5);
+
+        // Changes the key of the user and votes
+        let key_change = message(&vote(0, &new_keypair, 4, 10, 1), &users[0].0, 10);
+
+        let circuit = process_messages(&mut state, &[key_change]);
+
+        assert_eq!(
+            state.leaves[0].unwrap().public_key,
+            PublicKey::from(new_keypair.public_key)
+        );
+        assert_eq!(state.leaves[0].unwrap().voice_credit_balance, 0);
+        assert!(prove_and_verify(process_message_keys(), &circuit).is_ok());
+    }
+
+    #[test]
+    fn process_messages_ignores_messages_other_than_votes() {
+        let users = [(keypair(1), 100)];
+        let mut state = TestState::new(&users);
+        let initial_root = state.root();
+
+        let (data, ecdh_public_key) = message(&vote(0, &users[0].0, 2, 9, 1), &users[0].0, 10);
+        let other_message = (VOTE_MESSAGE_TYPE + 1, data, ecdh_public_key);
+
+        let mut circuit = process_typed_messages(&mut state, &[other_message]);
+
+        assert_eq!(circuit.new_state_root, initial_root);
+        assert!(prove_and_verify(process_message_keys(), &circuit).is_ok());
+
+        // Applying the command of the message as if it were a vote
+        let ballot = vec![0, 0, 9, 0, 0]
+            .into_iter()
+            .map(BlsScalar::from)
+            .collect::<Vec<_>>();
+        state.leaves[0] = Some(StateLeafOpening {
+            public_key: PublicKey::from(users[0].0.public_key),
+            voice_credit_balance: 19,
+            vote_option_tree_root: root_and_path(&ballot, VOTE_OPTION_TREE_ARITY, 0).0,
+            nonce: BlsScalar::one(),
+        });
+        circuit.new_state_root = state.root();
+
+        assert!(prove_and_verify(process_message_keys(), &circuit).is_err());
+    }
+
+    #[test]
+    fn process_messages_with_wrong_coordinator_key_fails() {
+        let users = [(keypair(1), 100)];
+        let mut state = TestState::new(&users);
+
+        let valid_vote = message(&vote(0, &users[0].0, 2, 9, 1), &users[0].0, 10);
+
+        let mut circuit = process_messages(&mut state, &[valid_vote]);
+        circuit.coordinator_private_key = keypair(1001).private_key.scalar();
+
+        assert!(prove_and_verify(process_message_keys(), &circuit).is_err());
+    }
+
+    /// State leaves of users who voted with the given ballots, followed by empty leaves.
+    fn tallied_state_leaves(
+        ballots: &[Vec<u64>],
+        number_of_leaves: usize,
+    ) -> Vec<TalliedStateLeaf> {
+        (0..number_of_leaves)
+            .map(|i| match ballots.get(i) {
+                Some(ballot) => {
+                    let weights: Vec<BlsScalar> =
+                        ballot.iter().map(|w| BlsScalar::from(*w)).collect();
+                    let spent_voice_credits: u64 = ballot.iter().map(|w| w * w).sum();
+
+                    TalliedStateLeaf {
+                        state_leaf: Some(StateLeafOpening {
+                            public_key: PublicKey::from(keypair(i as u64 + 1).public_key),
+                            voice_credit_balance: 100 - u128::from(spent_voice_credits),
+                            vote_option_tree_root: if spent_voice_credits == 0 {
+                                BlsScalar::zero()
+                            } else {
+                                root_and_path(&weights, VOTE_OPTION_TREE_ARITY, 0).0
+                            },
+                            nonce: BlsScalar::one(),
+                        }),
+                        ballot: ballot.clone(),
+                    }
+                }
+                None => TalliedStateLeaf::default(),
+            })
+            .collect()
+    }
+
+    /// Tallies a batch natively and builds the circuit proving it, returns the new tally as well.
+    fn tally_batch(
+        leaves: &[TalliedStateLeaf],
+        batch_index: usize,
+        current_tally: &Tally,
+        new_salt: u64,
+    ) -> (TestVoteTallyCircuit, Tally) {
+        let batch = &leaves[batch_index * STATE_TREE_ARITY..(batch_index + 1) * STATE_TREE_ARITY];
+
+        let batch_roots: Vec<BlsScalar> = leaves
+            .chunks(STATE_TREE_ARITY)
+            .map(|batch| {
+                let hashes: Vec<BlsScalar> = batch
+                    .iter()
+                    .map(|leaf| state_leaf_hash(&leaf.state_leaf))
+                    .collect();
+                sponge::hash(&hashes)
+            })
+            .collect();
+        let (state_root, state_path) = root_and_path(&batch_roots, STATE_TREE_ARITY, batch_index);
+
+        let mut new_tally = Tally {
+            results: current_tally.results.clone(),
+            total_spent_voice_credits: current_tally.total_spent_voice_credits,
+            salt: BlsScalar::from(new_salt),
+        };
+        for leaf in batch {
+            for (result, vote_weight) in new_tally.results.iter_mut().zip(leaf.ballot.iter()) {
+                *result += u128::from(*vote_weight);
+                new_tally.total_spent_voice_credits += u128::from(*vote_weight).pow(2);
+            }
+        }
+
+        let circuit = TestVoteTallyCircuit {
+            state_leaves: batch.to_vec(),
+            state_path,
+            current_tally: current_tally.clone(),
+            new_salt: new_tally.salt,
+            state_root,
+            batch_start_index: BlsScalar::from((batch_index * STATE_TREE_ARITY) as u64),
+            current_tally_commitment: if batch_index == 0 {
+                BlsScalar::zero()
+            } else {
+                current_tally.commitment()
+            },
+            new_tally_commitment: new_tally.commitment(),
+        };
+
+        (circuit, new_tally)
+    }
+
+    fn empty_tally() -> Tally {
+        Tally {
+            results: vec![0; VOTE_OPTION_TREE_ARITY],
+            ..Tally::default()
+        }
+    }
+
+    #[test]
+    fn tally_votes_of_every_batch_succeeds() {
+        let ballots = [
+            vec![1, 0, 3, 0, 0],
+            vec![0, 0, 0, 0, 0],
+            vec![0, 2, 2, 0, 0],
+            vec![5, 0, 0, 0, 0],
+            vec![0, 0, 0, 0, 9],
+            vec![0, 0, 4, 0, 1],
+            vec![0, 1, 0, 1, 0],
+        ];
+        let leaves = tallied_state_leaves(&ballots, STATE_TREE_ARITY.pow(2));
+
+        let (circuit, tally) = tally_batch(&leaves, 0, &empty_tally(), 10);
+        assert!(prove_and_verify(vote_tally_keys(), &circuit).is_ok());
+
+        let (circuit, tally) = tally_batch(&leaves, 1, &tally, 11);
+        assert!(prove_and_verify(vote_tally_keys(), &circuit).is_ok());
+
+        assert_eq!(tally.results, vec![6, 3, 9, 1, 10]);
+        assert_eq!(
+            tally.total_spent_voice_credits,
+            1 + 9 + 4 + 4 + 25 + 81 + 16 + 1 + 1 + 1
+        );
+    }
+
+    #[test]
+    fn tally_votes_with_wrong_results_fails() {
+        let ballots = [vec![1, 0, 3, 0, 0], vec![0, 2, 2, 0, 0]];
+        let leaves = tallied_state_leaves(&ballots, STATE_TREE_ARITY.pow(2));
+
+        let (mut circuit, mut tally) = tally_batch(&leaves, 0, &empty_tally(), 10);
+        tally.results[1] += 1;
+        circuit.new_tally_commitment = tally.commitment();
+
+        assert!(prove_and_verify(vote_tally_keys(), &circuit).is_err());
+    }
+
+    #[test]
+    fn tally_votes_of_empty_leaf_with_votes_fails() {
+        let ballots = [vec![1, 0, 3, 0, 0], vec![0, 2, 2, 0, 0]];
+        let mut leaves = tallied_state_leaves(&ballots, STATE_TREE_ARITY.pow(2));
+
+        // An empty leaf has no ballot, its votes would be counted in the new tally
+        leaves[3].ballot = vec![0, 0, 0, 7, 0];
+        let (circuit, _) = tally_batch(&leaves, 0, &empty_tally(), 10);
+
+        assert!(prove_and_verify(vote_tally_keys(), &circuit).is_err());
+    }
+
+    #[test]
+    fn tally_ballot_of_empty_leaf_succeeds() {
+        let circuit = BallotCircuit {
+            is_empty: true,
+            state_leaf: None,
+            ballot: Vec::new(),
+            state_leaf_hash: empty_leaf(),
+            vote_weights: vec![BlsScalar::zero(); VOTE_OPTION_TREE_ARITY],
+        };
+
+        assert!(prove_and_verify(ballot_keys(), &circuit).is_ok());
+    }
+
+    #[test]
+    fn tally_ballot_of_empty_leaf_opened_with_ballot_root_fails() {
+        let ballot = vec![0, 0, 0, 7, 0];
+        let vote_weights: Vec<BlsScalar> = ballot.iter().map(|w| BlsScalar::from(*w)).collect();
+        let (ballot_root, _) = root_and_path(&vote_weights, VOTE_OPTION_TREE_ARITY, 0);
+
+        // The leaf is marked empty, its opening carries the root of the invented votes
+        let circuit = BallotCircuit {
+            is_empty: true,
+            state_leaf: Some(StateLeafOpening {
+                public_key: PublicKey::from(keypair(1).public_key),
+                voice_credit_balance: 51,
+                vote_option_tree_root: ballot_root,
+                nonce: BlsScalar::one(),
+            }),
+            ballot,
+            state_leaf_hash: empty_leaf(),
+            vote_weights,
+        };
+
+        assert!(prove_and_verify(ballot_keys(), &circuit).is_err());
+    }
+}
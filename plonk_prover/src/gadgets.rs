@@ -0,0 +1,880 @@
+use dusk_hades::{GadgetStrategy, WIDTH};
+use dusk_jubjub::{JubJubAffine, JubJubScalar, EDWARDS_D, GENERATOR};
+use dusk_plonk::prelude::*;
+use dusk_poseidon::sponge;
+use maki_shared::poseidon_encryption::ciphertext_length;
+
+/// Number of bits of the canonical decomposition of a scalar.
+pub(crate) const SCALAR_BITS: usize = 255;
+
+/// Number of bits of a JubJub scalar.
+pub const JUBJUB_SCALAR_BITS: usize = 252;
+
+/// Returns a boolean witness, one if `value` is zero.
+pub(crate) fn is_zero<C: Composer>(composer: &mut C, value: Witness) -> Witness {
+    let inverse = composer[value].invert().unwrap_or_else(BlsScalar::zero);
+    let inverse = composer.append_witness(inverse);
+
+    let is_zero = if composer[value] == BlsScalar::zero() {
+        BlsScalar::one()
+    } else {
+        BlsScalar::zero()
+    };
+    let is_zero = composer.append_witness(is_zero);
+
+    // value * inverse + is_zero - 1 = 0
+    let constraint = Constraint::new()
+        .mult(1)
+        .output(1)
+        .constant(-BlsScalar::one())
+        .a(value)
+        .b(inverse)
+        .o(is_zero);
+    composer.append_gate(constraint);
+
+    // value * is_zero = 0
+    let constraint = Constraint::new().mult(1).a(value).b(is_zero);
+    composer.append_gate(constraint);
+
+    is_zero
+}
+
+/// Returns a boolean witness, one if `a` equals `b`.
+pub(crate) fn is_equal<C: Composer>(composer: &mut C, a: Witness, b: Witness) -> Witness {
+    let difference = sub(composer, a, b);
+
+    is_zero(composer, difference)
+}
+
+/// Returns a boolean witness, one if `a` equals the constant `b`.
+pub(crate) fn is_equal_constant<C: Composer>(
+    composer: &mut C,
+    a: Witness,
+    b: BlsScalar,
+) -> Witness {
+    let constraint = Constraint::new().left(1).constant(-b).a(a);
+    let difference = composer.gate_add(constraint);
+
+    is_zero(composer, difference)
+}
+
+/// Returns a boolean witness, one if both points are equal.
+pub(crate) fn is_equal_point<C: Composer>(
+    composer: &mut C,
+    a: WitnessPoint,
+    b: WitnessPoint,
+) -> Witness {
+    let x = is_equal(composer, *a.x(), *b.x());
+    let y = is_equal(composer, *a.y(), *b.y());
+
+    and(composer, x, y)
+}
+
+pub(crate) fn add<C: Composer>(composer: &mut C, a: Witness, b: Witness) -> Witness {
+    let constraint = Constraint::new().left(1).right(1).a(a).b(b);
+
+    composer.gate_add(constraint)
+}
+
+pub(crate) fn sub<C: Composer>(composer: &mut C, a: Witness, b: Witness) -> Witness {
+    let constraint = Constraint::new().left(1).right(-BlsScalar::one()).a(a).b(b);
+
+    composer.gate_add(constraint)
+}
+
+pub(crate) fn mul<C: Composer>(composer: &mut C, a: Witness, b: Witness) -> Witness {
+    let constraint = Constraint::new().mult(1).a(a).b(b);
+
+    composer.gate_mul(constraint)
+}
+
+/// Logical and of two boolean witnesses.
+pub(crate) fn and<C: Composer>(composer: &mut C, a: Witness, b: Witness) -> Witness {
+    mul(composer, a, b)
+}
+
+/// Logical and of boolean witnesses, one if `bits` is empty.
+pub(crate) fn and_all<C: Composer>(composer: &mut C, bits: &[Witness]) -> Witness {
+    bits.iter()
+        .fold(C::ONE, |result, bit| and(composer, result, *bit))
+}
+
+/// Logical not of a boolean witness.
+pub(crate) fn not<C: Composer>(composer: &mut C, bit: Witness) -> Witness {
+    let constraint = Constraint::new().left(-BlsScalar::one()).constant(1).a(bit);
+
+    composer.gate_add(constraint)
+}
+
+/// Asserts `a == b` if the boolean `enabled` is one, does not constrain `a` and `b` otherwise.
+pub(crate) fn assert_equal_if<C: Composer>(
+    composer: &mut C,
+    enabled: Witness,
+    a: Witness,
+    b: Witness,
+) {
+    let difference = sub(composer, a, b);
+
+    let constraint = Constraint::new().mult(1).a(enabled).b(difference);
+    composer.append_gate(constraint);
+}
+
+/// Sum of the little-endian `bits`.
+pub(crate) fn from_bits<C: Composer>(composer: &mut C, bits: &[Witness]) -> Witness {
+    bits.iter().enumerate().fold(C::ZERO, |sum, (i, bit)| {
+        let constraint = Constraint::new()
+            .left(1)
+            .right(BlsScalar::pow_of_2(i as u64))
+            .a(sum)
+            .b(*bit);
+
+        composer.gate_add(constraint)
+    })
+}
+
+/// Returns a boolean witness, one if the number of the little-endian `bits` is lower than `constant`,
+/// or equal to it when `or_equal` is set. `constant` must fit in as many bits.
+pub(crate) fn is_less_than_constant<C: Composer>(
+    composer: &mut C,
+    bits: &[Witness],
+    constant: &BlsScalar,
+    or_equal: bool,
+) -> Witness {
+    let constant_bits = constant.to_bits();
+    debug_assert!(constant_bits[bits.len()..].iter().all(|bit| *bit == 0));
+
+    // From the least significant bit, the number is lower than the constant if its bit is lower
+    // than the constant's bit, or if they are equal and the lower bits are lower
+    let initial = if or_equal { C::ONE } else { C::ZERO };
+
+    bits.iter()
+        .zip(constant_bits.iter())
+        .fold(initial, |lower, (bit, constant_bit)| {
+            let constraint = if *constant_bit == 1 {
+                // 1 - bit + bit * lower
+                Constraint::new()
+                    .mult(1)
+                    .left(-BlsScalar::one())
+                    .constant(1)
+                    .a(*bit)
+                    .b(lower)
+            } else {
+                // (1 - bit) * lower
+                Constraint::new()
+                    .mult(-BlsScalar::one())
+                    .right(1)
+                    .a(*bit)
+                    .b(lower)
+            };
+
+            composer.gate_add(constraint)
+        })
+}
+
+/// Decomposes `value` into its unique little-endian bits, the bits are constrained to be lower than the modulus.
+pub(crate) fn canonical_bits<C: Composer>(
+    composer: &mut C,
+    value: Witness,
+) -> [Witness; SCALAR_BITS] {
+    let bits = composer.component_decomposition::<SCALAR_BITS>(value);
+
+    let is_canonical = is_less_than_constant(composer, &bits, &-BlsScalar::one(), true);
+    composer.assert_equal_constant(is_canonical, BlsScalar::one(), None);
+
+    bits
+}
+
+/// One-hot position of `index` at each level of a tree of the given arity, from the leaves to the root.
+/// `index` must be lower than `ARITY^depth`. In a binary tree, the second witness of each position is
+/// the bit of the index of the level.
+pub fn tree_positions<C: Composer, const ARITY: usize>(
+    composer: &mut C,
+    index: Witness,
+    depth: usize,
+) -> Vec<[Witness; ARITY]> {
+    let limbs = composer[index].reduce().0;
+    let mut remaining = u128::from(limbs[0]) | (u128::from(limbs[1]) << 64);
+
+    let mut positions = Vec::with_capacity(depth);
+    let mut recomposed = C::ZERO;
+    let mut weight = BlsScalar::one();
+
+    for _ in 0..depth {
+        let digit = (remaining % ARITY as u128) as usize;
+        remaining /= ARITY as u128;
+
+        let position: [Witness; ARITY] = core::array::from_fn(|k| {
+            composer.append_witness(BlsScalar::from(u64::from(k == digit)))
+        });
+
+        let mut set_bits = C::ZERO;
+        for (k, bit) in position.iter().enumerate() {
+            composer.component_boolean(*bit);
+
+            set_bits = add(composer, set_bits, *bit);
+
+            let constraint = Constraint::new()
+                .left(1)
+                .right(weight * BlsScalar::from(k as u64))
+                .a(recomposed)
+                .b(*bit);
+            recomposed = composer.gate_add(constraint);
+        }
+        composer.assert_equal_constant(set_bits, BlsScalar::one(), None);
+
+        positions.push(position);
+        weight *= BlsScalar::from(ARITY as u64);
+    }
+
+    composer.assert_equal(recomposed, index);
+
+    positions
+}
+
+/// Root of the Merkle tree of the given arity holding `leaf` at `positions`, `siblings` are the other children
+/// of each level from left to right. Nodes are hashed as `maki::hasher::hash_children` does, the Poseidon sponge
+/// of the children from left to right, the leaves and the siblings being the scalars of their bytes.
+pub fn merkle_root<C: Composer, const ARITY: usize>(
+    composer: &mut C,
+    leaf: Witness,
+    positions: &[[Witness; ARITY]],
+    siblings: &[Vec<Witness>],
+) -> Witness {
+    positions
+        .iter()
+        .zip(siblings.iter())
+        .fold(leaf, |node, (position, siblings)| {
+            let mut children = [C::ZERO; ARITY];
+            // Set once the node has been placed, the children on its right are the siblings shifted by one
+            let mut placed = C::ZERO;
+
+            for (j, child) in children.iter_mut().enumerate() {
+                *child = mul(composer, position[j], node);
+
+                if j + 1 < ARITY {
+                    let not_placed = not(composer, placed);
+                    let left_of_node = sub(composer, not_placed, position[j]);
+                    let sibling = mul(composer, left_of_node, siblings[j]);
+                    *child = add(composer, *child, sibling);
+                }
+
+                if j > 0 {
+                    let sibling = mul(composer, placed, siblings[j - 1]);
+                    *child = add(composer, *child, sibling);
+                }
+
+                placed = add(composer, placed, position[j]);
+            }
+
+            sponge::gadget(composer, &children)
+        })
+}
+
+/// Appends the point of the given coordinates, which are not constrained to be on the curve.
+pub(crate) fn append_point_from_coordinates<C: Composer>(
+    composer: &mut C,
+    x: Witness,
+    y: Witness,
+) -> WitnessPoint {
+    let point = composer.append_point(JubJubAffine::from_raw_unchecked(composer[x], composer[y]));
+
+    composer.assert_equal(*point.x(), x);
+    composer.assert_equal(*point.y(), y);
+
+    point
+}
+
+/// Returns a boolean witness, one if the coordinates are a point of JubJub.
+pub(crate) fn is_on_curve<C: Composer>(composer: &mut C, x: Witness, y: Witness) -> Witness {
+    let x_square = mul(composer, x, x);
+    let y_square = mul(composer, y, y);
+    let x_square_y_square = mul(composer, x_square, y_square);
+
+    // -x^2 + y^2 - 1 - d * x^2 * y^2
+    let constraint = Constraint::new()
+        .left(-BlsScalar::one())
+        .right(1)
+        .fourth(-EDWARDS_D)
+        .constant(-BlsScalar::one())
+        .a(x_square)
+        .b(y_square)
+        .d(x_square_y_square);
+    let equation = composer.gate_add(constraint);
+
+    is_zero(composer, equation)
+}
+
+/// Returns a boolean witness, one if `point` is the identity.
+pub(crate) fn is_identity<C: Composer>(composer: &mut C, point: WitnessPoint) -> Witness {
+    let x = is_zero(composer, *point.x());
+    let y = is_equal_constant(composer, *point.y(), BlsScalar::one());
+
+    and(composer, x, y)
+}
+
+/// Returns a boolean witness, one if the coordinates are a point of the prime order subgroup other than
+/// the identity, as checked by `JubJubAffine::is_prime_order`. The returned point is the generator if
+/// the coordinates are not on the curve, so that it can be used in additions.
+pub(crate) fn is_prime_order_point<C: Composer>(
+    composer: &mut C,
+    x: Witness,
+    y: Witness,
+) -> (Witness, WitnessPoint) {
+    let on_curve = is_on_curve(composer, x, y);
+
+    let point = append_point_from_coordinates(composer, x, y);
+    let generator = composer.append_constant_point(GENERATOR);
+    let point = composer.component_select_point(on_curve, point, generator);
+
+    // The point is torsion free if multiplying it by the order of the subgroup gives the identity
+    let order_minus_one = BlsScalar::from(-JubJubScalar::one());
+    let multiplied = mul_point_constant(composer, &order_minus_one, point);
+    let multiplied = composer.component_add_point(multiplied, point);
+
+    let torsion_free = is_identity(composer, multiplied);
+    let identity = is_identity(composer, point);
+    let not_identity = not(composer, identity);
+
+    let is_prime_order = and_all(composer, &[on_curve, torsion_free, not_identity]);
+
+    (is_prime_order, point)
+}
+
+/// Multiplies `point` by the scalar of the little-endian `bits`.
+pub(crate) fn mul_point_bits<C: Composer>(
+    composer: &mut C,
+    bits: &[Witness],
+    point: WitnessPoint,
+) -> WitnessPoint {
+    bits.iter().rev().fold(C::IDENTITY, |result, bit| {
+        let result = composer.component_add_point(result, result);
+        let point_to_add = composer.component_select_identity(*bit, point);
+
+        composer.component_add_point(result, point_to_add)
+    })
+}
+
+/// Mirrors `maki_shared::ecdh::derive_shared_key`, `private_key_bits` are the little-endian bits of the private key
+/// as given by `component_decomposition::<JUBJUB_SCALAR_BITS>`.
+pub fn derive_shared_key<C: Composer>(
+    composer: &mut C,
+    private_key_bits: &[Witness],
+    public_key: WitnessPoint,
+) -> WitnessPoint {
+    mul_point_bits(composer, private_key_bits, public_key)
+}
+
+/// Multiplies `point` by a constant scalar.
+pub(crate) fn mul_point_constant<C: Composer>(
+    composer: &mut C,
+    scalar: &BlsScalar,
+    point: WitnessPoint,
+) -> WitnessPoint {
+    scalar
+        .to_bits()
+        .iter()
+        .rev()
+        .skip_while(|bit| **bit == 0)
+        .fold(C::IDENTITY, |result, bit| {
+            let result = composer.component_add_point(result, result);
+
+            if *bit == 1 {
+                composer.component_add_point(result, point)
+            } else {
+                result
+            }
+        })
+}
+
+/// Mirrors `maki_shared::eddsa::verify` for a `public_key` of the prime order subgroup, as the keys of the
+/// state leaves. Returns a boolean witness, one if the signature of coordinates `signature_r_x`,
+/// `signature_r_y` and scalar `signature_s` is a valid signature of `message`.
+pub fn verify_signature<C: Composer>(
+    composer: &mut C,
+    public_key: WitnessPoint,
+    message: Witness,
+    signature_r_x: Witness,
+    signature_r_y: Witness,
+    signature_s: Witness,
+) -> Witness {
+    let (is_r_valid, signature_r) = is_prime_order_point(composer, signature_r_x, signature_r_y);
+
+    // The scalar must be canonical, lower than the order of the subgroup
+    let signature_s_bits = canonical_bits(composer, signature_s);
+    let order_minus_one = BlsScalar::from(-JubJubScalar::one());
+    let is_s_valid = is_less_than_constant(composer, &signature_s_bits, &order_minus_one, true);
+
+    let challenge = sponge::truncated::gadget(
+        composer,
+        &[
+            signature_r_x,
+            signature_r_y,
+            *public_key.x(),
+            *public_key.y(),
+            message,
+        ],
+    );
+    let challenge_bits = composer.component_decomposition::<JUBJUB_SCALAR_BITS>(challenge);
+
+    // G * s == R + public_key * challenge
+    let generator = composer.append_constant_point(GENERATOR);
+    let signed_point = mul_point_bits(composer, &signature_s_bits[..JUBJUB_SCALAR_BITS], generator);
+    let challenged_key = mul_point_bits(composer, &challenge_bits, public_key);
+    let expected_point = composer.component_add_point(signature_r, challenged_key);
+    let is_equation_valid = is_equal_point(composer, signed_point, expected_point);
+
+    and_all(composer, &[is_r_valid, is_s_valid, is_equation_valid])
+}
+
+/// Mirrors `maki_shared::poseidon_encryption::decrypt` for a ciphertext of a known length.
+/// Returns the plaintext and a boolean witness, one if the decryption succeeded, the plaintext being
+/// meaningless otherwise.
+///
+/// Panics if the length of the ciphertext is not the one of a plaintext of `plaintext_length` elements.
+pub fn poseidon_decrypt<C: Composer>(
+    composer: &mut C,
+    ciphertext: &[Witness],
+    plaintext_length: usize,
+    shared_key: WitnessPoint,
+    nonce: Witness,
+) -> (Vec<Witness>, Witness) {
+    assert_eq!(
+        ciphertext.len(),
+        ciphertext_length(plaintext_length),
+        "invalid ciphertext length"
+    );
+
+    let (tag, ciphertext) = ciphertext.split_last().expect("ciphertext holds a tag");
+
+    let domain = composer.append_constant(BlsScalar::from_raw([0x100000000u64, 0, 0, 0]));
+    let length = composer.append_constant(BlsScalar::from(plaintext_length as u64));
+    let mut state = [domain, length, *shared_key.x(), *shared_key.y(), nonce];
+
+    GadgetStrategy::gadget(composer, &mut state);
+
+    let mut plaintext = Vec::with_capacity(ciphertext.len());
+    for chunk in ciphertext.chunks(WIDTH - 1) {
+        for (i, element) in chunk.iter().enumerate() {
+            plaintext.push(sub(composer, *element, state[i + 1]));
+            state[i + 1] = *element;
+        }
+
+        GadgetStrategy::gadget(composer, &mut state);
+    }
+
+    let tag_matches = is_equal(composer, *tag, state[1]);
+
+    // The padding must decrypt to zeros
+    let padding_is_zero: Vec<Witness> = plaintext[plaintext_length..]
+        .iter()
+        .map(|element| is_zero(composer, *element))
+        .collect();
+    let padding_is_zero = and_all(composer, &padding_is_zero);
+
+    let is_valid = and(composer, tag_matches, padding_is_zero);
+
+    plaintext.truncate(plaintext_length);
+
+    (plaintext, is_valid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use dusk_bytes::Serializable;
+    use dusk_jubjub::GENERATOR_EXTENDED;
+    use maki_shared::eddsa::{self, Keypair};
+    use maki_shared::functions_utils::{bytes_to_scalar, scalar_to_bytes};
+    use maki_shared::{ecdh, poseidon_encryption};
+    use rand_core::{OsRng, RngCore};
+
+    const PLAINTEXT_LENGTH: usize = 7;
+    const CIPHERTEXT_LENGTH: usize = ciphertext_length(PLAINTEXT_LENGTH);
+
+    /// Proves that a leaf is in the tree of the public root, at a private index.
+    #[derive(Debug, Default)]
+    struct MerklePathCircuit<const ARITY: usize, const DEPTH: usize> {
+        leaf: [u8; 32],
+        index: u64,
+        siblings: Vec<Vec<[u8; 32]>>,
+        root: [u8; 32],
+    }
+
+    impl<const ARITY: usize, const DEPTH: usize> Circuit for MerklePathCircuit<ARITY, DEPTH> {
+        fn circuit<C>(&self, composer: &mut C) -> Result<(), Error>
+        where
+            C: Composer,
+        {
+            let root = composer.append_public(bytes_to_scalar(self.root));
+
+            let leaf = composer.append_witness(bytes_to_scalar(self.leaf));
+            let index = composer.append_witness(BlsScalar::from(self.index));
+            let siblings: Vec<Vec<Witness>> = (0..DEPTH)
+                .map(|level| {
+                    (0..ARITY - 1)
+                        .map(|i| {
+                            let sibling = self
+                                .siblings
+                                .get(level)
+                                .map_or([0; 32], |siblings| siblings[i]);
+                            composer.append_witness(bytes_to_scalar(sibling))
+                        })
+                        .collect()
+                })
+                .collect();
+
+            let positions = tree_positions::<C, ARITY>(composer, index, DEPTH);
+            let path_root = merkle_root(composer, leaf, &positions, &siblings);
+
+            composer.assert_equal(path_root, root);
+
+            Ok(())
+        }
+    }
+
+    /// Proves that the public ciphertext decrypts to the public plaintext, with the shared key derived from
+    /// a private key and the public key of the other party.
+    #[derive(Debug, Default)]
+    struct DecryptionCircuit {
+        private_key: JubJubScalar,
+        public_key: JubJubAffine,
+        shared_key: JubJubAffine,
+        nonce: BlsScalar,
+        ciphertext: [BlsScalar; CIPHERTEXT_LENGTH],
+        plaintext: [BlsScalar; PLAINTEXT_LENGTH],
+    }
+
+    impl Circuit for DecryptionCircuit {
+        fn circuit<C>(&self, composer: &mut C) -> Result<(), Error>
+        where
+            C: Composer,
+        {
+            let nonce = composer.append_public(self.nonce);
+            let ciphertext: Vec<Witness> = self
+                .ciphertext
+                .iter()
+                .map(|element| composer.append_public(*element))
+                .collect();
+
+            let private_key = composer.append_witness(BlsScalar::from(self.private_key));
+            let private_key_bits =
+                composer.component_decomposition::<JUBJUB_SCALAR_BITS>(private_key);
+            let public_key = composer.append_point(self.public_key);
+
+            let shared_key = derive_shared_key(composer, &private_key_bits, public_key);
+            composer.assert_equal_public_point(shared_key, self.shared_key);
+
+            let (plaintext, is_decrypted) =
+                poseidon_decrypt(composer, &ciphertext, PLAINTEXT_LENGTH, shared_key, nonce);
+            composer.assert_equal_constant(is_decrypted, BlsScalar::one(), None);
+
+            for (element, expected) in plaintext.iter().zip(self.plaintext.iter()) {
+                let expected = composer.append_public(*expected);
+                composer.assert_equal(*element, expected);
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Encrypts a random plaintext with the native key exchange and encryption, the other party holding
+    /// the private key of the circuit.
+    fn random_decryption() -> DecryptionCircuit {
+        let private_key = JubJubScalar::random(&mut OsRng);
+        let other_private_key = JubJubScalar::random(&mut OsRng);
+        let public_key = JubJubAffine::from(GENERATOR_EXTENDED * other_private_key);
+        let own_public_key = JubJubAffine::from(GENERATOR_EXTENDED * private_key);
+
+        let shared_key = ecdh::derive_shared_key(&private_key, &public_key);
+        assert_eq!(
+            shared_key,
+            ecdh::derive_shared_key(&other_private_key, &own_public_key)
+        );
+
+        let nonce = BlsScalar::random(&mut OsRng);
+        let plaintext = [(); PLAINTEXT_LENGTH].map(|_| BlsScalar::random(&mut OsRng));
+        let ciphertext = poseidon_encryption::encrypt(&plaintext, &shared_key, &nonce)
+            .try_into()
+            .unwrap();
+
+        DecryptionCircuit {
+            private_key,
+            public_key,
+            shared_key,
+            nonce,
+            ciphertext,
+            plaintext,
+        }
+    }
+
+    /// Proves that the signature is valid or not, as publicly claimed.
+    #[derive(Debug, Default)]
+    struct SignatureCircuit {
+        public_key: JubJubAffine,
+        message: BlsScalar,
+        signature_r_x: BlsScalar,
+        signature_r_y: BlsScalar,
+        signature_s: BlsScalar,
+        is_valid: bool,
+    }
+
+    impl Circuit for SignatureCircuit {
+        fn circuit<C>(&self, composer: &mut C) -> Result<(), Error>
+        where
+            C: Composer,
+        {
+            let is_valid = composer.append_public(BlsScalar::from(u64::from(self.is_valid)));
+
+            let public_key = composer.append_point(self.public_key);
+            let message = composer.append_witness(self.message);
+            let signature_r_x = composer.append_witness(self.signature_r_x);
+            let signature_r_y = composer.append_witness(self.signature_r_y);
+            let signature_s = composer.append_witness(self.signature_s);
+
+            let is_signature_valid = verify_signature(
+                composer,
+                public_key,
+                message,
+                signature_r_x,
+                signature_r_y,
+                signature_s,
+            );
+            composer.assert_equal(is_signature_valid, is_valid);
+
+            Ok(())
+        }
+    }
+
+    /// Native signature of a random message by a random key.
+    fn random_signature() -> SignatureCircuit {
+        let keypair = Keypair::new(&mut OsRng);
+        let message = BlsScalar::random(&mut OsRng);
+        let signature = eddsa::sign(&keypair.private_key.scalar(), &message);
+
+        SignatureCircuit {
+            public_key: keypair.public_key,
+            message,
+            signature_r_x: signature.r.get_x(),
+            signature_r_y: signature.r.get_y(),
+            signature_s: BlsScalar::from(signature.s),
+            is_valid: eddsa::verify(&keypair.public_key, &message, &signature),
+        }
+    }
+
+    /// Random signatures, forged by changing the message, the key or the signature.
+    fn forged_signatures() -> Vec<SignatureCircuit> {
+        let mut wrong_message = random_signature();
+        wrong_message.message += BlsScalar::one();
+
+        let mut wrong_key = random_signature();
+        wrong_key.public_key = Keypair::new(&mut OsRng).public_key;
+
+        let mut wrong_r = random_signature();
+        let other_r = eddsa::sign(&JubJubScalar::random(&mut OsRng), &wrong_r.message).r;
+        wrong_r.signature_r_x = other_r.get_x();
+        wrong_r.signature_r_y = other_r.get_y();
+
+        // Not a point of the curve
+        let mut wrong_r_coordinates = random_signature();
+        wrong_r_coordinates.signature_r_x += BlsScalar::one();
+
+        let mut wrong_s = random_signature();
+        wrong_s.signature_s += BlsScalar::one();
+
+        // The signature equation holds for s plus the order of the subgroup, but s must be canonical
+        let mut non_canonical_s = random_signature();
+        non_canonical_s.signature_s += BlsScalar::from(-JubJubScalar::one()) + BlsScalar::one();
+
+        let mut forged = vec![wrong_message, wrong_key, wrong_r];
+        for circuit in forged.iter_mut() {
+            let signature = eddsa::Signature {
+                r: JubJubAffine::from_raw_unchecked(circuit.signature_r_x, circuit.signature_r_y),
+                s: JubJubScalar::from_bytes(&circuit.signature_s.to_bytes()).unwrap(),
+            };
+            circuit.is_valid = eddsa::verify(&circuit.public_key, &circuit.message, &signature);
+            assert!(!circuit.is_valid);
+        }
+
+        for mut circuit in [wrong_r_coordinates, wrong_s, non_canonical_s] {
+            circuit.is_valid = false;
+            forged.push(circuit);
+        }
+
+        forged
+    }
+
+    /// Same as `maki::hasher::hash_children`.
+    fn hash_children(children: &[[u8; 32]]) -> [u8; 32] {
+        let children: Vec<BlsScalar> = children
+            .iter()
+            .map(|child| bytes_to_scalar(*child))
+            .collect();
+
+        scalar_to_bytes(sponge::hash(&children))
+    }
+
+    fn random_bytes() -> [u8; 32] {
+        scalar_to_bytes(BlsScalar::random(&mut OsRng))
+    }
+
+    /// Random path of a random leaf, with the native root of the tree.
+    fn random_path<const ARITY: usize, const DEPTH: usize>() -> MerklePathCircuit<ARITY, DEPTH> {
+        let leaf = random_bytes();
+        let index = OsRng.next_u64() % (ARITY as u64).pow(DEPTH as u32);
+        let siblings: Vec<Vec<[u8; 32]>> = (0..DEPTH)
+            .map(|_| (0..ARITY - 1).map(|_| random_bytes()).collect())
+            .collect();
+
+        let mut node = leaf;
+        let mut position = index;
+        for level_siblings in siblings.iter() {
+            let mut children = level_siblings.clone();
+            children.insert((position % ARITY as u64) as usize, node);
+
+            node = hash_children(&children);
+            position /= ARITY as u64;
+        }
+
+        MerklePathCircuit {
+            leaf,
+            index,
+            siblings,
+            root: node,
+        }
+    }
+
+    fn prove_and_verify<C: Circuit>(circuits: &[C]) -> Vec<Result<(), Error>> {
+        let public_parameters = PublicParameters::setup(1 << 14, &mut OsRng).unwrap();
+        let (prover, verifier) = Compiler::compile::<C>(&public_parameters, b"gadgets").unwrap();
+
+        circuits
+            .iter()
+            .map(|circuit| {
+                let (proof, public_inputs) = prover.prove(&mut OsRng, circuit)?;
+                verifier.verify(&proof, &public_inputs)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn merkle_root_of_random_binary_paths_is_native_root() {
+        let circuits: Vec<MerklePathCircuit<2, 4>> = (0..3).map(|_| random_path()).collect();
+
+        for result in prove_and_verify(&circuits) {
+            assert!(result.is_ok());
+        }
+    }
+
+    #[test]
+    fn merkle_root_of_random_quinary_paths_is_native_root() {
+        let circuits: Vec<MerklePathCircuit<5, 3>> = (0..3).map(|_| random_path()).collect();
+
+        for result in prove_and_verify(&circuits) {
+            assert!(result.is_ok());
+        }
+    }
+
+    #[test]
+    fn merkle_root_of_wrong_path_is_not_native_root() {
+        let mut wrong_index = random_path::<5, 3>();
+        wrong_index.index = (wrong_index.index + 1) % 125;
+
+        let mut wrong_sibling = random_path::<5, 3>();
+        wrong_sibling.siblings[1][2] = random_bytes();
+
+        let mut wrong_leaf = random_path::<5, 3>();
+        wrong_leaf.leaf = random_bytes();
+
+        for result in prove_and_verify(&[wrong_index, wrong_sibling, wrong_leaf]) {
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn decryption_with_derived_shared_key_is_native_plaintext() {
+        let circuits: Vec<DecryptionCircuit> = (0..3).map(|_| random_decryption()).collect();
+
+        for result in prove_and_verify(&circuits) {
+            assert!(result.is_ok());
+        }
+    }
+
+    #[test]
+    fn decryption_with_wrong_key_fails() {
+        // The shared key matches the private key, but not the one of the encryption
+        let mut wrong_private_key = random_decryption();
+        wrong_private_key.private_key = JubJubScalar::random(&mut OsRng);
+        wrong_private_key.shared_key = ecdh::derive_shared_key(
+            &wrong_private_key.private_key,
+            &wrong_private_key.public_key,
+        );
+
+        let mut wrong_public_key = random_decryption();
+        wrong_public_key.public_key =
+            JubJubAffine::from(GENERATOR_EXTENDED * JubJubScalar::random(&mut OsRng));
+        wrong_public_key.shared_key =
+            ecdh::derive_shared_key(&wrong_public_key.private_key, &wrong_public_key.public_key);
+
+        // The shared key is not the one derived from the keys
+        let mut wrong_shared_key = random_decryption();
+        wrong_shared_key.shared_key = random_decryption().shared_key;
+
+        for result in prove_and_verify(&[wrong_private_key, wrong_public_key, wrong_shared_key]) {
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn decryption_of_tampered_ciphertext_fails() {
+        let mut wrong_element = random_decryption();
+        wrong_element.ciphertext[2] += BlsScalar::one();
+
+        let mut wrong_tag = random_decryption();
+        wrong_tag.ciphertext[CIPHERTEXT_LENGTH - 1] = BlsScalar::random(&mut OsRng);
+
+        let mut wrong_nonce = random_decryption();
+        wrong_nonce.nonce += BlsScalar::one();
+
+        // Decrypts to the same plaintext, but the padding is not zero
+        let mut wrong_padding = random_decryption();
+        wrong_padding.ciphertext[CIPHERTEXT_LENGTH - 2] += BlsScalar::one();
+
+        for result in prove_and_verify(&[wrong_element, wrong_tag, wrong_nonce, wrong_padding]) {
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn signature_verification_of_native_signatures_is_valid() {
+        let circuits: Vec<SignatureCircuit> = (0..3).map(|_| random_signature()).collect();
+        assert!(circuits.iter().all(|circuit| circuit.is_valid));
+
+        for result in prove_and_verify(&circuits) {
+            assert!(result.is_ok());
+        }
+    }
+
+    #[test]
+    fn signature_verification_of_forged_signatures_is_not_valid() {
+        for result in prove_and_verify(&forged_signatures()) {
+            assert!(result.is_ok());
+        }
+    }
+
+    #[test]
+    fn signature_verification_with_wrong_validity_fails() {
+        let mut valid = random_signature();
+        valid.is_valid = false;
+
+        let mut circuits = forged_signatures();
+        for circuit in circuits.iter_mut() {
+            circuit.is_valid = true;
+        }
+        circuits.push(valid);
+
+        for result in prove_and_verify(&circuits) {
+            assert!(result.is_err());
+        }
+    }
+}
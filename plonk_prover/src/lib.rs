@@ -1,16 +1,17 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
 mod circuits;
+pub mod gadgets;
 mod proof_verifier;
 mod proof_prover;
 
-pub use proof_prover::{proveVoteTally, proveProcessMessage};
-pub use proof_verifier::{verifyVoteTally, verifyProcessMessage};
-
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn verify_proof_process_message_success(){
-        
-    }
-}
\ No newline at end of file
+pub use circuits::{
+    ProcessedMessage, StateLeafOpening, Tally, TalliedStateLeaf, MESSAGE_BATCH_SIZE,
+    MESSAGE_TREE_DEPTH, NUMBER_OF_VOTE_OPTIONS, STATE_TREE_DEPTH, TALLY_BATCH_DEPTH,
+    TALLY_BATCH_SIZE, VOTE_OPTION_TREE_DEPTH,
+};
+pub use proof_prover::{prove_vote_tally, prove_process_message};
+pub use proof_verifier::{
+    process_message_verifier_key, verify_process_message, verify_vote_tally,
+    vote_tally_verifier_key,
+};
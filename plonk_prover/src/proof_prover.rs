@@ -1,75 +1,86 @@
 use dusk_bytes::Serializable;
 use dusk_plonk::prelude::*;
-use dusk_poseidon::sponge;
 use maki_shared::{
     functions_utils::bytes_to_scalar,
-    types::{PublicKey, SerializedProof, TreeRoot, PrivateKey},
+    types::{PoseidonHash, PublicKey, SerializedProof, TreeRoot, PrivateKey},
 };
 use rand_core::OsRng;
 
 use crate::circuits::*;
 
-pub fn proveVoteTally(
+/// Proves the tally of a batch of state leaves, `state_leaves` holds the leaves of the batch and their ballots.
+#[allow(clippy::too_many_arguments)]
+pub fn prove_vote_tally(
     public_parameters: &[u8],
     // private inputs
-    a: BlsScalar,
-    b: BlsScalar,
-    c: BlsScalar,
-    d: BlsScalar,
-    private_key: [u8; 32],
+    state_leaves: &[TalliedStateLeaf],
+    state_path: &[Vec<BlsScalar>],
+    current_tally: &Tally,
+    new_salt: BlsScalar,
     // public inputs
-    new_state_root: TreeRoot,
-    public_key: PublicKey,
+    state_root: TreeRoot,
+    batch_start_index: u32,
+    current_tally_commitment: PoseidonHash,
+    new_tally_commitment: PoseidonHash,
 ) -> Result<SerializedProof, Error> {
     //Read public parameters
     let pp = PublicParameters::from_slice(public_parameters)?;
 
-    let (prover, _) = Compiler::compile::<MakiVoteTallyCircuit>(&pp, LABEL_TRANSCRIPT)
-        .expect("failed to compile circuit");
+    let (prover, _) = Compiler::compile::<MakiVoteTallyCircuit>(&pp, LABEL_TRANSCRIPT)?;
 
-    let circuit = MakiVoteTallyCircuit {
-        a,
-        b,
-        c,
-        d,
-        hashed_private_key: sponge::hash(&[bytes_to_scalar(private_key)]),
-        new_state_root: sponge::hash(&[bytes_to_scalar(new_state_root)]),
-        public_key: sponge::hash(&[bytes_to_scalar(public_key)]),
+    let circuit: MakiVoteTallyCircuit = MakiVoteTallyCircuit {
+        state_leaves: state_leaves.to_vec(),
+        state_path: state_path.to_vec(),
+        current_tally: current_tally.clone(),
+        new_salt,
+        state_root: bytes_to_scalar(state_root),
+        batch_start_index: BlsScalar::from(u64::from(batch_start_index)),
+        current_tally_commitment: bytes_to_scalar(current_tally_commitment),
+        new_tally_commitment: bytes_to_scalar(new_tally_commitment),
     };
 
     // Generate the proof and its public inputs
-    let (proof, _) = prover.prove(&mut OsRng, &circuit).expect("Failed to prove");
+    let (proof, _) = prover.prove(&mut OsRng, &circuit)?;
 
-    return Ok(proof.to_bytes());
+    Ok(proof.to_bytes())
 }
 
-//TODO
-pub fn proveProcessMessage(
+/// Proves the processing of a batch of messages, `messages` holds the private inputs of each message of the batch.
+#[allow(clippy::too_many_arguments)]
+pub fn prove_process_message(
     public_parameters: &[u8],
     // private inputs
-    ecdh_private_key: PrivateKey,
-    private_key: PrivateKey,
+    coordinator_private_key: PrivateKey,
+    messages: &[ProcessedMessage],
     // public inputs
+    message_root: TreeRoot,
+    current_state_root: TreeRoot,
     new_state_root: TreeRoot,
-    public_key: PublicKey,
-    ecdh_public_key: PublicKey,
+    coordinator_public_key: PublicKey,
+    batch_start_index: u32,
+    batch_end_index: u32,
+    poll_id: u32,
 ) -> Result<SerializedProof, Error> {
     //Read public parameters
     let pp = PublicParameters::from_slice(public_parameters)?;
 
-    let (prover, _) = Compiler::compile::<MakiProcessMessageCircuit>(&pp, LABEL_TRANSCRIPT)
-        .expect("failed to compile circuit");
+    let (prover, _) = Compiler::compile::<MakiProcessMessageCircuit>(&pp, LABEL_TRANSCRIPT)?;
 
     let circuit: MakiProcessMessageCircuit = MakiProcessMessageCircuit {
-        hashed_private_key: sponge::hash(&[bytes_to_scalar(private_key)]),
+        coordinator_private_key: coordinator_private_key.scalar(),
+        messages: messages.to_vec(),
+        message_root: bytes_to_scalar(message_root),
+        current_state_root: bytes_to_scalar(current_state_root),
         new_state_root: bytes_to_scalar(new_state_root),
-        public_key: bytes_to_scalar(public_key),
-        ecdh_public_key: bytes_to_scalar(ecdh_public_key),
-        ecdh_private_key: bytes_to_scalar(ecdh_private_key),
+        coordinator_public_key_x: coordinator_public_key.x,
+        coordinator_public_key_y: coordinator_public_key.y,
+        batch_start_index: BlsScalar::from(u64::from(batch_start_index)),
+        batch_end_index: BlsScalar::from(u64::from(batch_end_index)),
+        poll_id: BlsScalar::from(u64::from(poll_id)),
     };
 
     // Generate the proof and its public inputs
-    let (proof, _) = prover.prove(&mut OsRng, &circuit).expect("Failed to prove");
+    let (proof, _) = prover.prove(&mut OsRng, &circuit)?;
 
-    return Ok(proof.to_bytes());
+    Ok(proof.to_bytes())
 }
@@ -1,48 +1,87 @@
 use dusk_bytes::Serializable;
 use dusk_plonk::prelude::*;
-use maki_shared::{types::{SerializedProof, TreeRoot, PublicKey}, functions_utils::bytes_to_scalar};
+use maki_shared::{types::{PoseidonHash, SerializedProof, TreeRoot, PublicKey}, functions_utils::bytes_to_scalar};
 
 use crate::circuits::*;
 
-// TODO : Add Public inputs and use accordingly
-pub fn verifyVoteTally(
-    public_parameters: &[u8],
-    proof: &SerializedProof,
-    f: JubJubAffine,
-) -> Result<(), Error> {
-    //Read public parameters
+/// Compiles the vote tally circuit over the public parameters, off-chain, and returns the verifier key polls are deployed with.
+pub fn vote_tally_verifier_key(public_parameters: &[u8]) -> Result<Vec<u8>, Error> {
+    let pp = PublicParameters::from_slice(public_parameters)?;
+
+    let (_, verifier) = Compiler::compile::<MakiVoteTallyCircuit>(&pp, LABEL_TRANSCRIPT)?;
+
+    Ok(verifier.to_bytes())
+}
+
+/// Compiles the process message circuit over the public parameters, off-chain, and returns the verifier key polls are deployed with.
+pub fn process_message_verifier_key(public_parameters: &[u8]) -> Result<Vec<u8>, Error> {
     let pp = PublicParameters::from_slice(public_parameters)?;
 
-    let (_, verifier) = Compiler::compile::<MakiVoteTallyCircuit>(&pp, LABEL_TRANSCRIPT)
-        .expect("failed to compile circuit");
+    let (_, verifier) = Compiler::compile::<MakiProcessMessageCircuit>(&pp, LABEL_TRANSCRIPT)?;
+
+    Ok(verifier.to_bytes())
+}
+
+pub fn verify_vote_tally(
+    verifier_key: &[u8],
+    state_root: TreeRoot,
+    batch_start_index: u32,
+    current_tally_commitment: PoseidonHash,
+    new_tally_commitment: PoseidonHash,
+    proof: &SerializedProof,
+) -> Result<(), Error> {
+    //Read verifier key
+    let verifier = Verifier::<MakiVoteTallyCircuit>::try_from_bytes(verifier_key)?;
 
     // Proof deserialization
     let proof = Proof::from_bytes(proof)?;
 
-    // Create public inputs
-    let public_inputs: Vec<BlsScalar> = vec![f.get_x(), f.get_y()];
+    // Create public inputs, the composer appends their negation to the circuit
+    let public_inputs: Vec<BlsScalar> = [
+        bytes_to_scalar(state_root),
+        BlsScalar::from(u64::from(batch_start_index)),
+        bytes_to_scalar(current_tally_commitment),
+        bytes_to_scalar(new_tally_commitment),
+    ]
+    .iter()
+    .map(|public_input| -public_input)
+    .collect();
 
     verifier.verify(&proof, &public_inputs)
 }
 
-pub fn verifyProcessMessage(
-    public_parameters: &[u8],
+#[allow(clippy::too_many_arguments)]
+pub fn verify_process_message(
+    verifier_key: &[u8],
+    message_root: TreeRoot,
+    current_state_root: TreeRoot,
     new_state_root: TreeRoot,
-    public_key: PublicKey,
-    ecdh_public_key: PublicKey,
+    coordinator_public_key: PublicKey,
+    batch_start_index: u32,
+    batch_end_index: u32,
+    poll_id: u32,
     proof: &SerializedProof,
 ) -> Result<(), Error> {
-    //Read public parameters
-    let pp = PublicParameters::from_slice(public_parameters)?;
-
-    let (_, verifier) = Compiler::compile::<MakiProcessMessageCircuit>(&pp, LABEL_TRANSCRIPT)
-        .expect("failed to compile circuit");
+    //Read verifier key
+    let verifier = Verifier::<MakiProcessMessageCircuit>::try_from_bytes(verifier_key)?;
 
     // Proof deserialization
     let proof = Proof::from_bytes(proof)?;
 
-    // Create public inputs
-    let public_inputs: Vec<BlsScalar> = vec![bytes_to_scalar(new_state_root), bytes_to_scalar(public_key), bytes_to_scalar(ecdh_public_key)];
+    // Create public inputs, the composer appends their negation to the circuit
+    let public_inputs: Vec<BlsScalar> = [
+        bytes_to_scalar(message_root),
+        bytes_to_scalar(current_state_root),
+        bytes_to_scalar(new_state_root),
+        coordinator_public_key.x,
+        coordinator_public_key.y,
+        BlsScalar::from(u64::from(batch_start_index)),
+        BlsScalar::from(u64::from(batch_end_index)),
+        BlsScalar::from(u64::from(poll_id)),
+    ]
+    .iter()
+    .map(|public_input| -public_input)
+    .collect();
 
     verifier.verify(&proof, &public_inputs)
 }
@@ -0,0 +1,24 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// Sign-up gatekeeper used by the registry end-to-end tests, it approves the sign ups of a single account.
+#[ink::contract]
+pub mod gatekeeper_mock {
+
+    #[ink(storage)]
+    pub struct GatekeeperMock {
+        approved_account: AccountId,
+    }
+
+    impl GatekeeperMock {
+        #[ink(constructor)]
+        pub fn new(approved_account: AccountId) -> Self {
+            Self { approved_account }
+        }
+
+        /// Called by the registry on each sign up, approves it if `account` is the approved account.
+        #[ink(message)]
+        pub fn register(&self, account: AccountId) -> bool {
+            account == self.approved_account
+        }
+    }
+}
@@ -0,0 +1,32 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+/// PSP22 token used by the registry end-to-end tests, a single account holds the whole supply.
+#[ink::contract]
+pub mod psp22_mock {
+
+    #[ink(storage)]
+    pub struct Psp22Mock {
+        holder: AccountId,
+        total_supply: Balance,
+    }
+
+    impl Psp22Mock {
+        #[ink(constructor)]
+        pub fn new(holder: AccountId, total_supply: Balance) -> Self {
+            Self {
+                holder,
+                total_supply,
+            }
+        }
+
+        /// Balance of `owner`, with the selector of `PSP22::balance_of` queried by the voice credit proxy.
+        #[ink(message, selector = 0x6568382f)]
+        pub fn balance_of(&self, owner: AccountId) -> Balance {
+            if owner == self.holder {
+                self.total_supply
+            } else {
+                0
+            }
+        }
+    }
+}
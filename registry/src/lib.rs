@@ -0,0 +1,1161 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[ink::contract]
+pub mod maki_registry {
+
+    // The Poll contract being a dependency, events have to be emitted explicitly on behalf of the registry
+    use ink::codegen::EmitEvent;
+    use ink::env::call::{build_call, ExecutionInput, Selector};
+    use ink::prelude::vec::Vec;
+    use ink::storage::Mapping;
+    use ink::ToAccountId;
+    use maki::hasher::hash_state_leaf;
+    use maki::maki_objects::{SignUpGatekeeper, StateLeaf, VoiceCreditProxy};
+    use maki::maki_types::{PollId, PublicKey, VoiceCreditBalance};
+    use maki::merkle_tree::{MerkleTreeError, QuinaryMerkleTree};
+    use maki::poll::PollRef;
+    use maki_shared::types::TreeRoot;
+    use plonk_prover::{MESSAGE_BATCH_SIZE, MESSAGE_TREE_DEPTH, STATE_TREE_DEPTH};
+
+    #[ink(storage)]
+    pub struct MakiRegistry {
+        owner: AccountId,
+
+        // Voice credits granted to users when signing up
+        voice_credit_proxy: VoiceCreditProxy,
+        voice_credits_table: Mapping<AccountId, VoiceCreditBalance>,
+
+        // Sign-up rules
+        gatekeeper: SignUpGatekeeper,
+        allowlist: Mapping<AccountId, ()>,
+        signed_up_accounts: Mapping<AccountId, ()>,
+
+        // State, shared by all the polls. The state tree is quinary to keep it shallow
+        state_tree: QuinaryMerkleTree,
+        number_sign_ups: u32,
+
+        // Polls
+        poll_code_hash: Hash,
+        bond_beneficiary: AccountId,
+        polls: Mapping<PollId, AccountId>,
+        next_poll_id: PollId,
+    }
+
+    /// Errors which may be returned from the smart contract
+    #[derive(Debug, PartialEq, Eq, scale::Encode, scale::Decode)]
+    #[cfg_attr(feature = "std", derive(scale_info::TypeInfo))]
+    pub enum Error {
+        SignUpNotAllowed,
+        NotOwner,
+        VoiceCreditProxyFailed,
+        PollDeploymentFailed,
+        InvalidPublicKey,
+        InvalidTreeDepth,
+        SignUpLimitReached,
+        StateTreeError,
+        InvalidMessageBatchSize,
+        InvalidVerifierKey,
+    }
+
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// SignedUp event when a user signed up successfully
+    #[ink(event)]
+    pub struct SignedUp {
+        user_public_key: PublicKey,
+        voice_credit_balance: VoiceCreditBalance,
+    }
+
+    /// PollDeployed event when the owner deployed a new poll
+    #[ink(event)]
+    pub struct PollDeployed {
+        poll_id: PollId,
+        poll: AccountId,
+    }
+
+    impl MakiRegistry {
+        /// The state tree has the depth the circuits are compiled with, so that every poll can be proved against it.
+        /// ## Arguments
+        ///
+        /// * `poll_code_hash` - Code hash of the uploaded Poll contract, used to deploy the polls
+        ///
+        /// * `bond_beneficiary` - Account the coordinator's bond of a poll is paid to if the poll is voided
+        #[ink(constructor)]
+        pub fn new(
+            voice_credit_proxy: VoiceCreditProxy,
+            gatekeeper: SignUpGatekeeper,
+            poll_code_hash: Hash,
+            bond_beneficiary: AccountId,
+        ) -> Self {
+            Self {
+                owner: Self::env().caller(),
+                voice_credit_proxy,
+                voice_credits_table: Mapping::default(),
+                gatekeeper,
+                allowlist: Mapping::default(),
+                signed_up_accounts: Mapping::default(),
+                state_tree: QuinaryMerkleTree::new(STATE_TREE_DEPTH as u8)
+                    .expect("STATE_TREE_DEPTH is a valid tree depth"),
+                number_sign_ups: 0,
+                poll_code_hash,
+                bond_beneficiary,
+                polls: Mapping::default(),
+                next_poll_id: 0,
+            }
+        }
+
+        /// Sign Up can be called by any user whishing to cast a vote, as long as the gatekeeper allows it.
+        /// Signed up users can vote in every poll deployed afterwards.
+        /// ## Arguments
+        ///
+        /// * `user_public_key` - User's public key that will be used by the coordinator to decrypt commands (encrypted using a shared key)
+        ///
+        /// ## Returns
+        #[ink(message)]
+        pub fn sign_up(&mut self, user_public_key: PublicKey) -> Result<()> {
+            if !user_public_key.is_valid() {
+                return Err(Error::InvalidPublicKey);
+            }
+
+            let caller = self.env().caller();
+
+            if !self.is_sign_up_allowed(caller) {
+                return Err(Error::SignUpNotAllowed);
+            }
+
+            let voice_credit_balance = self.voice_credits_of(caller)?;
+
+            let state_leaf =
+                StateLeaf::new(user_public_key, voice_credit_balance, [0; 32], [0; 32]);
+
+            let hashed_leaf = hash_state_leaf(&state_leaf);
+
+            self.state_tree
+                .insert_leaf(hashed_leaf)
+                .map_err(|error| match error {
+                    MerkleTreeError::TreeIsFull => Error::SignUpLimitReached,
+                    // Not returned when inserting a leaf, reported rather than trapping if it ever changes
+                    MerkleTreeError::InvalidTreeDepth
+                    | MerkleTreeError::LeafNotFound
+                    | MerkleTreeError::InvalidPath
+                    | MerkleTreeError::QueueAlreadyMerging
+                    | MerkleTreeError::SubrootsNotMerged => Error::StateTreeError,
+                })?;
+
+            if self.gatekeeper == SignUpGatekeeper::OneSignUpPerAccount {
+                self.signed_up_accounts.insert(caller, &());
+            }
+
+            self.number_sign_ups += 1;
+
+            EmitEvent::<MakiRegistry>::emit_event(
+                self.env(),
+                SignedUp {
+                    user_public_key,
+                    voice_credit_balance,
+                },
+            );
+
+            Ok(())
+        }
+
+        /// Deploys a new poll from the stored Poll code hash, snapshotting the current state root. Can only be called by the owner.
+        /// The coordinator then posts its bond to the poll with `post_bond`, before users can publish messages.
+        /// ## Arguments
+        ///
+        /// * `vote_duration_seconds` - Duration of the voting phase, starting at the deployment
+        ///
+        /// * `processing_duration_seconds` - Time given to the coordinator to process the messages once the voting phase ended
+        ///
+        /// * `tallying_duration_seconds` - Time given to the coordinator to tally the votes once the processing deadline passed
+        ///
+        /// * `coordinator` - Account posting the coordinator's bond, which is refunded to it once the tally is published
+        ///
+        /// * `coordinator_public_key` - Public key of the coordinator processing the messages of the poll
+        ///
+        /// * `message_tree_depth` - Depth of the poll's binary message tree, must be the one the circuits are compiled with
+        ///
+        /// * `message_batch_size` - Number of messages processed in each batch, must be the one the circuits are compiled with
+        ///
+        /// * `process_message_verifier_key` - Verifier key of the process message circuit, compiled off-chain over the public parameters
+        ///
+        /// * `vote_tally_verifier_key` - Verifier key of the vote tally circuit, compiled off-chain over the public parameters
+        ///
+        /// ## Returns
+        #[ink(message)]
+        #[allow(clippy::too_many_arguments)]
+        pub fn deploy_poll(
+            &mut self,
+            vote_duration_seconds: u32,
+            processing_duration_seconds: u32,
+            tallying_duration_seconds: u32,
+            coordinator: AccountId,
+            coordinator_public_key: PublicKey,
+            message_tree_depth: u8,
+            message_batch_size: u32,
+            process_message_verifier_key: Vec<u8>,
+            vote_tally_verifier_key: Vec<u8>,
+        ) -> Result<PollId> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+
+            if !coordinator_public_key.is_valid() {
+                return Err(Error::InvalidPublicKey);
+            }
+
+            // The message tree is distinct from the state tree, it must have the depth of its circuit too
+            if usize::from(message_tree_depth) != MESSAGE_TREE_DEPTH {
+                return Err(Error::InvalidTreeDepth);
+            }
+
+            if message_batch_size as usize != MESSAGE_BATCH_SIZE {
+                return Err(Error::InvalidMessageBatchSize);
+            }
+
+            // The keys are only deserialized when a proof is verified, a poll without them could never be proved
+            if process_message_verifier_key.is_empty() || vote_tally_verifier_key.is_empty() {
+                return Err(Error::InvalidVerifierKey);
+            }
+
+            let poll_id = self.next_poll_id;
+
+            let poll = PollRef::new(
+                poll_id,
+                vote_duration_seconds,
+                processing_duration_seconds,
+                tallying_duration_seconds,
+                coordinator,
+                coordinator_public_key,
+                self.bond_beneficiary,
+                self.state_tree.get_root(),
+                self.number_sign_ups,
+                STATE_TREE_DEPTH as u8,
+                message_tree_depth,
+                message_batch_size,
+                process_message_verifier_key,
+                vote_tally_verifier_key,
+            )
+            .code_hash(self.poll_code_hash)
+            .endowment(0)
+            .salt_bytes(poll_id.to_le_bytes())
+            .try_instantiate();
+
+            let poll = match poll {
+                Ok(Ok(Ok(poll))) => poll.to_account_id(),
+                _ => return Err(Error::PollDeploymentFailed),
+            };
+
+            self.polls.insert(poll_id, &poll);
+            self.next_poll_id += 1;
+
+            EmitEvent::<MakiRegistry>::emit_event(self.env(), PollDeployed { poll_id, poll });
+
+            Ok(poll_id)
+        }
+
+        /// Allows an account to sign up when the gatekeeper is an allowlist. Can only be called by the owner.
+        /// ## Arguments
+        ///
+        /// * `account` - Account allowed to sign up
+        ///
+        /// ## Returns
+        #[ink(message)]
+        pub fn add_to_allowlist(&mut self, account: AccountId) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+
+            self.allowlist.insert(account, &());
+
+            Ok(())
+        }
+
+        /// Disallows an account to sign up when the gatekeeper is an allowlist. Can only be called by the owner.
+        /// ## Arguments
+        ///
+        /// * `account` - Account no longer allowed to sign up
+        ///
+        /// ## Returns
+        #[ink(message)]
+        pub fn remove_from_allowlist(&mut self, account: AccountId) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+
+            self.allowlist.remove(account);
+
+            Ok(())
+        }
+
+        /// Sets the voice credits granted to an account when signing up, when the voice credit proxy is a table.
+        /// Can only be called by the owner.
+        /// ## Arguments
+        ///
+        /// * `account` - Account the voice credits are granted to
+        ///
+        /// * `voice_credit_balance` - Voice credits granted to the account
+        ///
+        /// ## Returns
+        #[ink(message)]
+        pub fn set_voice_credits(
+            &mut self,
+            account: AccountId,
+            voice_credit_balance: VoiceCreditBalance,
+        ) -> Result<()> {
+            if self.env().caller() != self.owner {
+                return Err(Error::NotOwner);
+            }
+
+            self.voice_credits_table
+                .insert(account, &voice_credit_balance);
+
+            Ok(())
+        }
+
+        /// Returns the address of a deployed poll.
+        #[ink(message)]
+        pub fn get_poll(&self, poll_id: PollId) -> Option<AccountId> {
+            self.polls.get(poll_id)
+        }
+
+        /// Returns the current root of the state tree, which is snapshotted by the polls deployed afterwards.
+        #[ink(message)]
+        pub fn get_state_root(&self) -> TreeRoot {
+            self.state_tree.get_root()
+        }
+
+        /// Returns the number of users signed up, whose state leaves are tallied by the polls deployed afterwards.
+        #[ink(message)]
+        pub fn get_number_sign_ups(&self) -> u32 {
+            self.number_sign_ups
+        }
+
+        /// Returns whether `root` is one of the recent roots of the state tree, so that inclusion proofs against it can still be checked.
+        #[ink(message)]
+        pub fn is_known_state_root(&self, root: TreeRoot) -> bool {
+            self.state_tree.is_known_root(&root)
+        }
+
+        /// Returns the source of the voice credits granted to users when signing up.
+        #[ink(message)]
+        pub fn get_voice_credit_proxy(&self) -> VoiceCreditProxy {
+            self.voice_credit_proxy
+        }
+
+        /// Returns the rules deciding which accounts are allowed to sign up.
+        #[ink(message)]
+        pub fn get_gatekeeper(&self) -> SignUpGatekeeper {
+            self.gatekeeper
+        }
+
+        fn is_sign_up_allowed(&self, account: AccountId) -> bool {
+            match self.gatekeeper {
+                SignUpGatekeeper::Open => true,
+                SignUpGatekeeper::Allowlist => self.allowlist.contains(account),
+                SignUpGatekeeper::OneSignUpPerAccount => !self.signed_up_accounts.contains(account),
+                SignUpGatekeeper::Contract(gatekeeper) => {
+                    let approval = build_call::<Environment>()
+                        .call(gatekeeper)
+                        .exec_input(
+                            ExecutionInput::new(Selector::new(ink::selector_bytes!("register")))
+                                .push_arg(account),
+                        )
+                        .returns::<bool>()
+                        .try_invoke();
+
+                    matches!(approval, Ok(Ok(true)))
+                }
+            }
+        }
+
+        fn voice_credits_of(&self, account: AccountId) -> Result<VoiceCreditBalance> {
+            match self.voice_credit_proxy {
+                VoiceCreditProxy::Constant(voice_credit_balance) => Ok(voice_credit_balance),
+                VoiceCreditProxy::Table => {
+                    Ok(self.voice_credits_table.get(account).unwrap_or_default())
+                }
+                VoiceCreditProxy::Psp22(token) => {
+                    let balance = build_call::<Environment>()
+                        .call(token)
+                        .exec_input(
+                            ExecutionInput::new(Selector::new(ink::selector_bytes!(
+                                "PSP22::balance_of"
+                            )))
+                            .push_arg(account),
+                        )
+                        .returns::<Balance>()
+                        .try_invoke();
+
+                    match balance {
+                        Ok(Ok(balance)) => Ok(balance),
+                        _ => Err(Error::VoiceCreditProxyFailed),
+                    }
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        // Imports all the definitions from the outer scope so we can use them here.
+        use super::*;
+
+        use dusk_jubjub::{JubJubAffine, JubJubScalar, GENERATOR_EXTENDED};
+
+        type Event = <MakiRegistry as ::ink::reflect::ContractEventBase>::Type;
+
+        /// Valid public key whose private key is `seed`.
+        fn test_public_key(seed: u64) -> PublicKey {
+            PublicKey::from(JubJubAffine::from(
+                GENERATOR_EXTENDED * JubJubScalar::from(seed),
+            ))
+        }
+
+        #[ink::test]
+        fn sign_up_emits_sign_up_event() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut registry = MakiRegistry::new(
+                VoiceCreditProxy::Constant(100),
+                SignUpGatekeeper::Open,
+                Hash::default(),
+                accounts.django,
+            );
+
+            let upk = test_public_key(1);
+
+            let result = registry.sign_up(upk);
+
+            assert!(result.is_ok());
+
+            let events = ink::env::test::recorded_events().collect::<Vec<_>>();
+
+            let events_length = &events.len();
+
+            assert_eq!(*events_length, 1);
+            let sign_up_event = &events[0];
+            let decoded_event = <Event as scale::Decode>::decode(&mut &sign_up_event.data[..])
+                .expect("encountered invalid contract event data buffer");
+            if let Event::SignedUp(SignedUp {
+                user_public_key,
+                voice_credit_balance,
+            }) = decoded_event
+            {
+                assert_eq!(
+                    user_public_key, upk,
+                    "encountered invalid SignedUp.user_public_key"
+                );
+                assert_eq!(
+                    voice_credit_balance, 100,
+                    "encountered invalid SignedUp.voice_credit_balance"
+                );
+            } else {
+                panic!("encountered unexpected event kind: expected a SignedUp event")
+            }
+        }
+
+        #[ink::test]
+        fn sign_up_with_invalid_public_key_returns_error() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut registry = MakiRegistry::new(
+                VoiceCreditProxy::Constant(100),
+                SignUpGatekeeper::Open,
+                Hash::default(),
+                accounts.django,
+            );
+
+            let empty_state_root = registry.get_state_root();
+
+            let result = registry.sign_up(PublicKey::from(JubJubAffine::identity()));
+
+            assert!(result.is_err());
+            assert_eq!(result, Err(Error::InvalidPublicKey));
+            assert_eq!(registry.get_state_root(), empty_state_root);
+        }
+
+        #[ink::test]
+        fn sign_up_updates_state_root() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut registry = MakiRegistry::new(
+                VoiceCreditProxy::Constant(100),
+                SignUpGatekeeper::Open,
+                Hash::default(),
+                accounts.django,
+            );
+
+            let empty_state_root = registry.get_state_root();
+
+            registry.sign_up(test_public_key(1)).unwrap();
+
+            assert_ne!(registry.get_state_root(), empty_state_root);
+            assert_eq!(registry.get_number_sign_ups(), 1);
+        }
+
+        #[ink::test]
+        fn state_root_before_sign_up_is_known() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut registry = MakiRegistry::new(
+                VoiceCreditProxy::Constant(100),
+                SignUpGatekeeper::Open,
+                Hash::default(),
+                accounts.django,
+            );
+
+            let empty_state_root = registry.get_state_root();
+
+            registry.sign_up(test_public_key(1)).unwrap();
+            registry.sign_up(test_public_key(2)).unwrap();
+
+            assert!(registry.is_known_state_root(empty_state_root));
+            assert!(registry.is_known_state_root(registry.get_state_root()));
+            assert!(!registry.is_known_state_root([1; 32]));
+        }
+
+        #[ink::test]
+        fn sign_up_once_state_tree_is_full_returns_error() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut registry = MakiRegistry::new(
+                VoiceCreditProxy::Constant(100),
+                SignUpGatekeeper::Open,
+                Hash::default(),
+                accounts.django,
+            );
+            // A tree of depth 1 is full after 5 sign-ups, the one of the circuits can not be filled in a test
+            registry.state_tree = QuinaryMerkleTree::new(1).unwrap();
+
+            for seed in 1..=5 {
+                registry.sign_up(test_public_key(seed)).unwrap();
+            }
+
+            let full_state_root = registry.get_state_root();
+
+            let result = registry.sign_up(test_public_key(6));
+
+            assert_eq!(result, Err(Error::SignUpLimitReached));
+            assert_eq!(registry.get_number_sign_ups(), 5);
+            assert_eq!(registry.get_state_root(), full_state_root);
+            assert_eq!(ink::env::test::recorded_events().count(), 5);
+        }
+
+        #[ink::test]
+        fn sign_up_with_open_gatekeeper_allows_multiple_sign_ups_per_account() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut registry = MakiRegistry::new(
+                VoiceCreditProxy::Constant(100),
+                SignUpGatekeeper::Open,
+                Hash::default(),
+                accounts.django,
+            );
+
+            assert!(registry.sign_up(test_public_key(1)).is_ok());
+            assert!(registry.sign_up(test_public_key(2)).is_ok());
+        }
+
+        #[ink::test]
+        fn sign_up_with_allowlist_gatekeeper_only_allows_allowlisted_accounts() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut registry = MakiRegistry::new(
+                VoiceCreditProxy::Constant(100),
+                SignUpGatekeeper::Allowlist,
+                Hash::default(),
+                accounts.django,
+            );
+
+            registry.add_to_allowlist(accounts.bob).unwrap();
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert!(registry.sign_up(test_public_key(1)).is_ok());
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            let result = registry.sign_up(test_public_key(2));
+
+            assert!(result.is_err());
+            assert_eq!(result, Err(Error::SignUpNotAllowed));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.alice);
+            registry.remove_from_allowlist(accounts.bob).unwrap();
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            let result = registry.sign_up(test_public_key(3));
+
+            assert_eq!(result, Err(Error::SignUpNotAllowed));
+        }
+
+        #[ink::test]
+        fn add_to_allowlist_by_other_than_owner_returns_error() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut registry = MakiRegistry::new(
+                VoiceCreditProxy::Constant(100),
+                SignUpGatekeeper::Allowlist,
+                Hash::default(),
+                accounts.django,
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            let result = registry.add_to_allowlist(accounts.bob);
+
+            assert!(result.is_err());
+            assert_eq!(result, Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn sign_up_with_one_sign_up_per_account_gatekeeper_rejects_second_sign_up() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut registry = MakiRegistry::new(
+                VoiceCreditProxy::Constant(100),
+                SignUpGatekeeper::OneSignUpPerAccount,
+                Hash::default(),
+                accounts.django,
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert!(registry.sign_up(test_public_key(1)).is_ok());
+
+            let result = registry.sign_up(test_public_key(2));
+
+            assert!(result.is_err());
+            assert_eq!(result, Err(Error::SignUpNotAllowed));
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert!(registry.sign_up(test_public_key(3)).is_ok());
+        }
+
+        #[ink::test]
+        fn sign_up_with_table_voice_credit_proxy_grants_uploaded_voice_credits() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut registry = MakiRegistry::new(
+                VoiceCreditProxy::Table,
+                SignUpGatekeeper::Open,
+                Hash::default(),
+                accounts.django,
+            );
+
+            registry.set_voice_credits(accounts.bob, 42).unwrap();
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            assert!(registry.sign_up(test_public_key(1)).is_ok());
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.charlie);
+            assert!(registry.sign_up(test_public_key(2)).is_ok());
+
+            let voice_credit_balances: Vec<VoiceCreditBalance> = ink::env::test::recorded_events()
+                .map(|event| {
+                    let decoded_event = <Event as scale::Decode>::decode(&mut &event.data[..])
+                        .expect("encountered invalid contract event data buffer");
+                    if let Event::SignedUp(SignedUp {
+                        voice_credit_balance,
+                        ..
+                    }) = decoded_event
+                    {
+                        voice_credit_balance
+                    } else {
+                        panic!("encountered unexpected event kind: expected a SignedUp event")
+                    }
+                })
+                .collect();
+
+            assert_eq!(voice_credit_balances, vec![42, 0]);
+        }
+
+        #[ink::test]
+        fn set_voice_credits_by_other_than_owner_returns_error() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut registry = MakiRegistry::new(
+                VoiceCreditProxy::Table,
+                SignUpGatekeeper::Open,
+                Hash::default(),
+                accounts.django,
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            let result = registry.set_voice_credits(accounts.bob, 42);
+
+            assert!(result.is_err());
+            assert_eq!(result, Err(Error::NotOwner));
+        }
+
+        #[ink::test]
+        fn deploy_poll_by_other_than_owner_returns_error() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut registry = MakiRegistry::new(
+                VoiceCreditProxy::Constant(100),
+                SignUpGatekeeper::Open,
+                Hash::default(),
+                accounts.django,
+            );
+
+            ink::env::test::set_caller::<ink::env::DefaultEnvironment>(accounts.bob);
+            let result = registry.deploy_poll(
+                60,
+                60,
+                60,
+                accounts.bob,
+                test_public_key(1),
+                MESSAGE_TREE_DEPTH as u8,
+                MESSAGE_BATCH_SIZE as u32,
+                Vec::new(),
+                Vec::new(),
+            );
+
+            assert!(result.is_err());
+            assert_eq!(result, Err(Error::NotOwner));
+            assert_eq!(registry.get_poll(0), None);
+        }
+
+        #[ink::test]
+        fn deploy_poll_with_message_tree_depth_other_than_circuit_returns_error() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut registry = MakiRegistry::new(
+                VoiceCreditProxy::Constant(100),
+                SignUpGatekeeper::Open,
+                Hash::default(),
+                accounts.django,
+            );
+
+            let result = registry.deploy_poll(
+                60,
+                60,
+                60,
+                accounts.bob,
+                test_public_key(1),
+                MESSAGE_TREE_DEPTH as u8 + 1,
+                MESSAGE_BATCH_SIZE as u32,
+                Vec::new(),
+                Vec::new(),
+            );
+
+            assert_eq!(result, Err(Error::InvalidTreeDepth));
+            assert_eq!(registry.get_poll(0), None);
+        }
+
+        #[ink::test]
+        fn deploy_poll_with_message_batch_size_other_than_circuit_returns_error() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut registry = MakiRegistry::new(
+                VoiceCreditProxy::Constant(100),
+                SignUpGatekeeper::Open,
+                Hash::default(),
+                accounts.django,
+            );
+
+            let result = registry.deploy_poll(
+                60,
+                60,
+                60,
+                accounts.bob,
+                test_public_key(1),
+                MESSAGE_TREE_DEPTH as u8,
+                MESSAGE_BATCH_SIZE as u32 + 1,
+                vec![1],
+                vec![1],
+            );
+
+            assert_eq!(result, Err(Error::InvalidMessageBatchSize));
+            assert_eq!(registry.get_poll(0), None);
+        }
+
+        #[ink::test]
+        fn deploy_poll_with_empty_verifier_key_returns_error() {
+            let accounts = ink::env::test::default_accounts::<ink::env::DefaultEnvironment>();
+            let mut registry = MakiRegistry::new(
+                VoiceCreditProxy::Constant(100),
+                SignUpGatekeeper::Open,
+                Hash::default(),
+                accounts.django,
+            );
+
+            for (process_message_verifier_key, vote_tally_verifier_key) in
+                [(Vec::new(), vec![1]), (vec![1], Vec::new())]
+            {
+                let result = registry.deploy_poll(
+                    60,
+                    60,
+                    60,
+                    accounts.bob,
+                    test_public_key(1),
+                    MESSAGE_TREE_DEPTH as u8,
+                    MESSAGE_BATCH_SIZE as u32,
+                    process_message_verifier_key,
+                    vote_tally_verifier_key,
+                );
+
+                assert_eq!(result, Err(Error::InvalidVerifierKey));
+            }
+            assert_eq!(registry.get_poll(0), None);
+        }
+    }
+
+    /// End-to-end tests, run against a substrate-contracts-node with `cargo test --features e2e-tests`.
+    /// They cover the sign up rules and the voice credits relying on other contracts, and the deployment of polls.
+    #[cfg(all(test, feature = "e2e-tests"))]
+    mod e2e_tests {
+        use super::*;
+
+        use dusk_jubjub::{JubJubAffine, JubJubScalar, GENERATOR_EXTENDED};
+        use dusk_plonk::prelude::PublicParameters;
+        use gatekeeper_mock::gatekeeper_mock::GatekeeperMockRef;
+        use ink_e2e::{build_message, AccountKeyring, CallResult, PolkadotConfig};
+        use maki::poll::PollRef;
+        use plonk_prover::{process_message_verifier_key, vote_tally_verifier_key};
+        use psp22_mock::psp22_mock::Psp22MockRef;
+        use rand_core::OsRng;
+
+        type E2EResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+        type Event = <MakiRegistry as ::ink::reflect::ContractEventBase>::Type;
+
+        /// Valid public key whose private key is `seed`.
+        fn test_public_key(seed: u64) -> PublicKey {
+            PublicKey::from(JubJubAffine::from(
+                GENERATOR_EXTENDED * JubJubScalar::from(seed),
+            ))
+        }
+
+        /// Decodes the events emitted by `contract` during a call.
+        fn contract_events<V>(
+            result: &CallResult<PolkadotConfig, ink::env::DefaultEnvironment, V>,
+            contract: &AccountId,
+        ) -> Vec<Event> {
+            result
+                .events
+                .iter()
+                .map(|event| event.expect("encountered invalid runtime event"))
+                .filter(|event| {
+                    event.pallet_name() == "Contracts" && event.variant_name() == "ContractEmitted"
+                })
+                .filter_map(|event| {
+                    let (emitter, data) =
+                        <(AccountId, Vec<u8>) as scale::Decode>::decode(&mut event.field_bytes())
+                            .expect("encountered invalid ContractEmitted event");
+
+                    (emitter == *contract).then(|| {
+                        <Event as scale::Decode>::decode(&mut &data[..])
+                            .expect("encountered invalid contract event data buffer")
+                    })
+                })
+                .collect()
+        }
+
+        #[ink_e2e::test(additional_contracts = "registry/mocks/gatekeeper_mock/Cargo.toml")]
+        async fn sign_up_with_contract_gatekeeper_follows_gatekeeper_approval(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            let gatekeeper_constructor =
+                GatekeeperMockRef::new(ink_e2e::account_id(AccountKeyring::Bob));
+            let gatekeeper = client
+                .instantiate(
+                    "gatekeeper_mock",
+                    &ink_e2e::alice(),
+                    gatekeeper_constructor,
+                    0,
+                    None,
+                )
+                .await
+                .expect("gatekeeper instantiation failed")
+                .account_id;
+
+            let registry_constructor = MakiRegistryRef::new(
+                VoiceCreditProxy::Constant(100),
+                SignUpGatekeeper::Contract(gatekeeper),
+                Hash::default(),
+                ink_e2e::account_id(AccountKeyring::Ferdie),
+            );
+            let registry = client
+                .instantiate(
+                    "maki_registry",
+                    &ink_e2e::alice(),
+                    registry_constructor,
+                    0,
+                    None,
+                )
+                .await
+                .expect("registry instantiation failed")
+                .account_id;
+
+            let sign_up = build_message::<MakiRegistryRef>(registry)
+                .call(|registry| registry.sign_up(test_public_key(1)));
+            let result = client
+                .call(&ink_e2e::bob(), sign_up, 0, None)
+                .await
+                .expect("approved sign up failed");
+
+            assert_eq!(result.return_value(), Ok(()));
+
+            // The sign up reverts, it is only dry run to read the error
+            let sign_up = build_message::<MakiRegistryRef>(registry)
+                .call(|registry| registry.sign_up(test_public_key(2)));
+            let result = client
+                .call_dry_run(&ink_e2e::charlie(), &sign_up, 0, None)
+                .await;
+
+            assert_eq!(result.return_value(), Err(Error::SignUpNotAllowed));
+
+            let get_number_sign_ups = build_message::<MakiRegistryRef>(registry)
+                .call(|registry| registry.get_number_sign_ups());
+            let number_sign_ups = client
+                .call_dry_run(&ink_e2e::alice(), &get_number_sign_ups, 0, None)
+                .await
+                .return_value();
+
+            assert_eq!(number_sign_ups, 1);
+
+            Ok(())
+        }
+
+        #[ink_e2e::test(additional_contracts = "registry/mocks/psp22_mock/Cargo.toml")]
+        async fn sign_up_with_psp22_voice_credit_proxy_grants_token_balance(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            let token_constructor = Psp22MockRef::new(ink_e2e::account_id(AccountKeyring::Bob), 42);
+            let token = client
+                .instantiate("psp22_mock", &ink_e2e::alice(), token_constructor, 0, None)
+                .await
+                .expect("token instantiation failed")
+                .account_id;
+
+            let registry_constructor = MakiRegistryRef::new(
+                VoiceCreditProxy::Psp22(token),
+                SignUpGatekeeper::Open,
+                Hash::default(),
+                ink_e2e::account_id(AccountKeyring::Ferdie),
+            );
+            let registry = client
+                .instantiate(
+                    "maki_registry",
+                    &ink_e2e::alice(),
+                    registry_constructor,
+                    0,
+                    None,
+                )
+                .await
+                .expect("registry instantiation failed")
+                .account_id;
+
+            let upk = test_public_key(1);
+
+            let sign_up =
+                build_message::<MakiRegistryRef>(registry).call(|registry| registry.sign_up(upk));
+            let result = client
+                .call(&ink_e2e::bob(), sign_up, 0, None)
+                .await
+                .expect("sign up failed");
+
+            let events = contract_events(&result, &registry);
+
+            assert_eq!(events.len(), 1);
+            if let Event::SignedUp(SignedUp {
+                user_public_key,
+                voice_credit_balance,
+            }) = &events[0]
+            {
+                assert_eq!(*user_public_key, upk);
+                assert_eq!(*voice_credit_balance, 42);
+            } else {
+                panic!("encountered unexpected event kind: expected a SignedUp event")
+            }
+
+            // The state leaf holds the token balance too
+            let mut state_tree = QuinaryMerkleTree::new(STATE_TREE_DEPTH as u8).unwrap();
+            state_tree
+                .insert_leaf(hash_state_leaf(&StateLeaf::new(upk, 42, [0; 32], [0; 32])))
+                .unwrap();
+
+            let get_state_root = build_message::<MakiRegistryRef>(registry)
+                .call(|registry| registry.get_state_root());
+            let state_root = client
+                .call_dry_run(&ink_e2e::alice(), &get_state_root, 0, None)
+                .await
+                .return_value();
+
+            assert_eq!(state_root, state_tree.get_root());
+
+            Ok(())
+        }
+
+        #[ink_e2e::test(additional_contracts = "registry/mocks/psp22_mock/Cargo.toml")]
+        async fn sign_up_with_failing_psp22_voice_credit_proxy_returns_error(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            // No contract is deployed at the address of the token
+            let registry_constructor = MakiRegistryRef::new(
+                VoiceCreditProxy::Psp22(ink_e2e::account_id(AccountKeyring::Dave)),
+                SignUpGatekeeper::Open,
+                Hash::default(),
+                ink_e2e::account_id(AccountKeyring::Ferdie),
+            );
+            let registry = client
+                .instantiate(
+                    "maki_registry",
+                    &ink_e2e::alice(),
+                    registry_constructor,
+                    0,
+                    None,
+                )
+                .await
+                .expect("registry instantiation failed")
+                .account_id;
+
+            let sign_up = build_message::<MakiRegistryRef>(registry)
+                .call(|registry| registry.sign_up(test_public_key(1)));
+            let result = client
+                .call_dry_run(&ink_e2e::bob(), &sign_up, 0, None)
+                .await;
+
+            assert_eq!(result.return_value(), Err(Error::VoiceCreditProxyFailed));
+
+            Ok(())
+        }
+
+        #[ink_e2e::test(additional_contracts = "maki/Cargo.toml")]
+        async fn deploy_poll_instantiates_poll_with_registry_state(
+            mut client: ink_e2e::Client<C, E>,
+        ) -> E2EResult<()> {
+            let coordinator_bond = 1_000_000_000;
+
+            // The circuits are compiled off-chain, the public parameters must fit the process message circuit
+            let public_parameters = PublicParameters::setup(1 << 19, &mut OsRng)
+                .expect("public parameters setup failed")
+                .to_var_bytes();
+            let process_message_verifier_key = process_message_verifier_key(&public_parameters)
+                .expect("process message circuit compilation failed");
+            let vote_tally_verifier_key = vote_tally_verifier_key(&public_parameters)
+                .expect("vote tally circuit compilation failed");
+
+            let poll_code_hash = client
+                .upload("maki", &ink_e2e::alice(), None)
+                .await
+                .expect("poll upload failed")
+                .code_hash;
+
+            let registry_constructor = MakiRegistryRef::new(
+                VoiceCreditProxy::Constant(100),
+                SignUpGatekeeper::Open,
+                poll_code_hash,
+                ink_e2e::account_id(AccountKeyring::Ferdie),
+            );
+            let registry = client
+                .instantiate(
+                    "maki_registry",
+                    &ink_e2e::alice(),
+                    registry_constructor,
+                    0,
+                    None,
+                )
+                .await
+                .expect("registry instantiation failed")
+                .account_id;
+
+            for (signer, seed) in [(ink_e2e::bob(), 1), (ink_e2e::charlie(), 2)] {
+                let sign_up = build_message::<MakiRegistryRef>(registry)
+                    .call(|registry| registry.sign_up(test_public_key(seed)));
+                client
+                    .call(&signer, sign_up, 0, None)
+                    .await
+                    .expect("sign up failed");
+            }
+
+            let get_state_root = build_message::<MakiRegistryRef>(registry)
+                .call(|registry| registry.get_state_root());
+            let state_root = client
+                .call_dry_run(&ink_e2e::alice(), &get_state_root, 0, None)
+                .await
+                .return_value();
+
+            let deploy_poll = build_message::<MakiRegistryRef>(registry).call(|registry| {
+                registry.deploy_poll(
+                    60,
+                    60,
+                    60,
+                    ink_e2e::account_id(AccountKeyring::Dave),
+                    test_public_key(3),
+                    MESSAGE_TREE_DEPTH as u8,
+                    MESSAGE_BATCH_SIZE as u32,
+                    process_message_verifier_key.clone(),
+                    vote_tally_verifier_key.clone(),
+                )
+            });
+            let result = client
+                .call(&ink_e2e::alice(), deploy_poll, 0, None)
+                .await
+                .expect("poll deployment failed");
+
+            let get_poll =
+                build_message::<MakiRegistryRef>(registry).call(|registry| registry.get_poll(0));
+            let poll = client
+                .call_dry_run(&ink_e2e::alice(), &get_poll, 0, None)
+                .await
+                .return_value()
+                .expect("deployed poll is not stored");
+
+            let events = contract_events(&result, &registry);
+
+            assert_eq!(events.len(), 1);
+            if let Event::PollDeployed(PollDeployed {
+                poll_id,
+                poll: deployed_poll,
+            }) = &events[0]
+            {
+                assert_eq!(*poll_id, 0);
+                assert_eq!(*deployed_poll, poll);
+            } else {
+                panic!("encountered unexpected event kind: expected a PollDeployed event")
+            }
+
+            assert_eq!(result.return_value(), Ok(0));
+
+            // The coordinator posts its bond to the deployed poll
+            let post_bond = build_message::<PollRef>(poll).call(|poll| poll.post_bond());
+            client
+                .call(&ink_e2e::dave(), post_bond, coordinator_bond, None)
+                .await
+                .expect("bond posting failed");
+
+            // The poll snapshotted the registry's state and holds the bond
+            let get_poll_id = build_message::<PollRef>(poll).call(|poll| poll.get_poll_id());
+            let get_state_root = build_message::<PollRef>(poll).call(|poll| poll.get_state_root());
+            let get_number_sign_ups =
+                build_message::<PollRef>(poll).call(|poll| poll.get_number_sign_ups());
+            let get_coordinator_bond =
+                build_message::<PollRef>(poll).call(|poll| poll.get_coordinator_bond());
+
+            let alice = ink_e2e::alice();
+            assert_eq!(
+                client
+                    .call_dry_run(&alice, &get_poll_id, 0, None)
+                    .await
+                    .return_value(),
+                0
+            );
+            assert_eq!(
+                client
+                    .call_dry_run(&alice, &get_state_root, 0, None)
+                    .await
+                    .return_value(),
+                state_root
+            );
+            assert_eq!(
+                client
+                    .call_dry_run(&alice, &get_number_sign_ups, 0, None)
+                    .await
+                    .return_value(),
+                2
+            );
+            assert_eq!(
+                client
+                    .call_dry_run(&alice, &get_coordinator_bond, 0, None)
+                    .await
+                    .return_value(),
+                coordinator_bond
+            );
+            assert!(
+                client
+                    .balance(poll)
+                    .await
+                    .expect("poll balance query failed")
+                    >= coordinator_bond
+            );
+
+            Ok(())
+        }
+    }
+}
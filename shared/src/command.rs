@@ -0,0 +1,357 @@
+use alloc::vec::Vec;
+use dusk_bls12_381::BlsScalar;
+use dusk_bytes::Serializable;
+use dusk_jubjub::{JubJubAffine, JubJubScalar};
+
+use crate::eddsa::{self, Signature};
+use crate::functions_utils::scalar_to_bytes;
+use crate::poseidon_encryption::{self, ciphertext_length};
+use crate::types::{PoseidonHash, PublicKey};
+
+/// Type of the messages holding an encrypted vote command, the only type of message processed by the coordinator.
+pub const VOTE_MESSAGE_TYPE: u8 = 1;
+
+/// Number of bits each packed field of a command is limited to.
+pub const COMMAND_FIELD_BITS: u32 = 50;
+
+/// Number of field elements a command is packed into.
+pub const PACKED_COMMAND_LENGTH: usize = 4;
+
+const COMMAND_FIELD_MASK: u64 = (1 << COMMAND_FIELD_BITS) - 1;
+
+/// Number of fields packed into the first element of a packed command.
+pub const PACKED_FIELDS: u32 = 5;
+
+/// Number of field elements of a message's plaintext, the packed command followed by the `R` coordinates
+/// and the `s` scalar of its signature.
+pub const MESSAGE_PLAINTEXT_LENGTH: usize = PACKED_COMMAND_LENGTH + 3;
+
+/// Number of field elements of the ciphertext of a command.
+pub const MESSAGE_CIPHERTEXT_LENGTH: usize = ciphertext_length(MESSAGE_PLAINTEXT_LENGTH);
+
+/// Maximum number of field elements of a message's ciphertext.
+pub const MESSAGE_DATA_MAX_LENGTH: usize = 10;
+
+/// Every message is encrypted with a new ephemeral key, the nonce of the encryption is therefore constant.
+pub const MESSAGE_ENCRYPTION_NONCE: BlsScalar = BlsScalar::zero();
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CommandError {
+    FieldTooLarge,
+    InvalidPackedCommand,
+}
+
+/// A user's command, the plaintext of a message.
+///
+/// The numeric fields are packed into a single field element as
+/// `nonce | state_index << 50 | vote_option_index << 100 | new_vote_weight << 150 | poll_id << 200`,
+/// followed by the coordinates of the new public key and the salt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Command {
+    pub state_index: u64,
+    pub new_public_key: PublicKey,
+    pub vote_option_index: u64,
+    pub new_vote_weight: u64,
+    pub nonce: u64,
+    pub poll_id: u64,
+    pub salt: BlsScalar,
+}
+
+impl Command {
+    pub fn new(
+        state_index: u64,
+        new_public_key: PublicKey,
+        vote_option_index: u64,
+        new_vote_weight: u64,
+        nonce: u64,
+        poll_id: u64,
+        salt: BlsScalar,
+    ) -> Self {
+        Self {
+            state_index,
+            new_public_key,
+            vote_option_index,
+            new_vote_weight,
+            nonce,
+            poll_id,
+            salt,
+        }
+    }
+
+    /// Packs the command into field elements, every numeric field must fit in `COMMAND_FIELD_BITS` bits.
+    pub fn pack(&self) -> Result<[BlsScalar; PACKED_COMMAND_LENGTH], CommandError> {
+        let fields = [
+            self.nonce,
+            self.state_index,
+            self.vote_option_index,
+            self.new_vote_weight,
+            self.poll_id,
+        ];
+
+        if fields.iter().any(|field| *field > COMMAND_FIELD_MASK) {
+            return Err(CommandError::FieldTooLarge);
+        }
+
+        let mut limbs = [0u64; 4];
+        for (i, field) in fields.iter().enumerate() {
+            write_bits(&mut limbs, i as u32 * COMMAND_FIELD_BITS, *field);
+        }
+
+        Ok([
+            BlsScalar::from_raw(limbs),
+            self.new_public_key.x,
+            self.new_public_key.y,
+            self.salt,
+        ])
+    }
+
+    /// Unpacks a command packed with `pack`.
+    pub fn unpack(packed: &[BlsScalar; PACKED_COMMAND_LENGTH]) -> Result<Self, CommandError> {
+        let limbs = packed[0].reduce().0;
+
+        let unused_bits_offset = PACKED_FIELDS * COMMAND_FIELD_BITS;
+        if limbs[3] >> (unused_bits_offset - 192) != 0 {
+            return Err(CommandError::InvalidPackedCommand);
+        }
+
+        Ok(Self {
+            nonce: read_bits(&limbs, 0),
+            state_index: read_bits(&limbs, COMMAND_FIELD_BITS),
+            vote_option_index: read_bits(&limbs, 2 * COMMAND_FIELD_BITS),
+            new_vote_weight: read_bits(&limbs, 3 * COMMAND_FIELD_BITS),
+            poll_id: read_bits(&limbs, 4 * COMMAND_FIELD_BITS),
+            new_public_key: PublicKey::new(packed[1], packed[2]),
+            salt: packed[3],
+        })
+    }
+
+    /// Poseidon hash of the packed command, signed by the user.
+    pub fn hash(&self) -> Result<PoseidonHash, CommandError> {
+        Ok(scalar_to_bytes(self.hash_scalar()?))
+    }
+
+    /// Signs the command hash with the private key the user is currently signed up with.
+    pub fn sign(&self, private_key: &JubJubScalar) -> Result<Signature, CommandError> {
+        Ok(eddsa::sign(private_key, &self.hash_scalar()?))
+    }
+
+    /// Verifies that the command was signed by the owner of `public_key`.
+    pub fn verify_signature(&self, public_key: &PublicKey, signature: &Signature) -> bool {
+        match (public_key.to_affine(), self.hash_scalar()) {
+            (Some(public_key), Ok(hash)) => eddsa::verify(&public_key, &hash, signature),
+            _ => false,
+        }
+    }
+
+    /// Encrypts the packed command and its signature with the ECDH shared key, giving the data of a message.
+    pub fn encrypt(
+        &self,
+        signature: &Signature,
+        shared_key: &JubJubAffine,
+    ) -> Result<Vec<BlsScalar>, CommandError> {
+        let mut plaintext = Vec::with_capacity(MESSAGE_PLAINTEXT_LENGTH);
+        plaintext.extend_from_slice(&self.pack()?);
+        plaintext.extend_from_slice(&[
+            signature.r.get_x(),
+            signature.r.get_y(),
+            BlsScalar::from(signature.s),
+        ]);
+
+        Ok(poseidon_encryption::encrypt(
+            &plaintext,
+            shared_key,
+            &MESSAGE_ENCRYPTION_NONCE,
+        ))
+    }
+
+    /// Decrypts the data of a message encrypted with `encrypt`. Returns `None` if the data can not be decrypted
+    /// with the shared key, or does not hold a packed command and a signature with `R` of prime order
+    /// and a canonical `s`.
+    pub fn decrypt(data: &[BlsScalar], shared_key: &JubJubAffine) -> Option<(Self, Signature)> {
+        let plaintext = poseidon_encryption::decrypt(
+            data,
+            MESSAGE_PLAINTEXT_LENGTH,
+            shared_key,
+            &MESSAGE_ENCRYPTION_NONCE,
+        )?;
+
+        let mut packed = [BlsScalar::zero(); PACKED_COMMAND_LENGTH];
+        packed.copy_from_slice(&plaintext[..PACKED_COMMAND_LENGTH]);
+        let command = Self::unpack(&packed).ok()?;
+
+        let signature = Signature {
+            r: PublicKey::new(plaintext[4], plaintext[5]).to_affine()?,
+            s: JubJubScalar::from_bytes(&plaintext[6].to_bytes()).ok()?,
+        };
+
+        Some((command, signature))
+    }
+
+    fn hash_scalar(&self) -> Result<BlsScalar, CommandError> {
+        let packed = self.pack()?;
+
+        Ok(dusk_poseidon::sponge::hash(&packed))
+    }
+}
+
+/// Writes a `COMMAND_FIELD_BITS` bits value at `offset` in little-endian limbs.
+fn write_bits(limbs: &mut [u64; 4], offset: u32, value: u64) {
+    let limb = (offset / 64) as usize;
+    let shift = offset % 64;
+
+    limbs[limb] |= value << shift;
+    if shift + COMMAND_FIELD_BITS > 64 {
+        limbs[limb + 1] |= value >> (64 - shift);
+    }
+}
+
+/// Reads the `COMMAND_FIELD_BITS` bits value at `offset` in little-endian limbs.
+fn read_bits(limbs: &[u64; 4], offset: u32) -> u64 {
+    let limb = (offset / 64) as usize;
+    let shift = offset % 64;
+
+    let mut value = limbs[limb] >> shift;
+    if shift + COMMAND_FIELD_BITS > 64 {
+        value |= limbs[limb + 1] << (64 - shift);
+    }
+
+    value & COMMAND_FIELD_MASK
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::eddsa::Keypair;
+    use crate::types::PrivateKey;
+
+    const MAX_FIELD: u64 = COMMAND_FIELD_MASK;
+
+    fn test_public_key(seed: u64) -> PublicKey {
+        PublicKey::from(
+            Keypair::from_private_key(PrivateKey::from(JubJubScalar::from(seed))).public_key,
+        )
+    }
+
+    fn test_command() -> Command {
+        Command::new(3, test_public_key(1), 2, 9, 1, 0, BlsScalar::from(7))
+    }
+
+    #[test]
+    fn pack_then_unpack_returns_same_command() {
+        let command = test_command();
+
+        let packed = command.pack().unwrap();
+
+        assert_eq!(Command::unpack(&packed), Ok(command));
+    }
+
+    #[test]
+    fn pack_then_unpack_with_max_fields_returns_same_command() {
+        let command = Command::new(
+            MAX_FIELD,
+            test_public_key(1),
+            MAX_FIELD,
+            MAX_FIELD,
+            MAX_FIELD,
+            MAX_FIELD,
+            -BlsScalar::one(),
+        );
+
+        let packed = command.pack().unwrap();
+
+        assert_eq!(Command::unpack(&packed), Ok(command));
+    }
+
+    #[test]
+    fn pack_places_fields_at_their_offsets() {
+        let command = Command::new(2, test_public_key(1), 3, 4, 1, 5, BlsScalar::zero());
+
+        let packed = command.pack().unwrap();
+
+        let expected = BlsScalar::from(1)
+            + BlsScalar::from(2) * BlsScalar::from(2).pow(&[50, 0, 0, 0])
+            + BlsScalar::from(3) * BlsScalar::from(2).pow(&[100, 0, 0, 0])
+            + BlsScalar::from(4) * BlsScalar::from(2).pow(&[150, 0, 0, 0])
+            + BlsScalar::from(5) * BlsScalar::from(2).pow(&[200, 0, 0, 0]);
+
+        assert_eq!(packed[0], expected);
+    }
+
+    #[test]
+    fn pack_with_too_large_field_returns_error() {
+        let mut command = test_command();
+        command.new_vote_weight = MAX_FIELD + 1;
+
+        assert_eq!(command.pack(), Err(CommandError::FieldTooLarge));
+        assert_eq!(command.hash(), Err(CommandError::FieldTooLarge));
+    }
+
+    #[test]
+    fn unpack_with_bits_over_packed_fields_returns_error() {
+        let mut packed = test_command().pack().unwrap();
+        packed[0] += BlsScalar::from(2).pow(&[250, 0, 0, 0]);
+
+        assert_eq!(
+            Command::unpack(&packed),
+            Err(CommandError::InvalidPackedCommand)
+        );
+    }
+
+    #[test]
+    fn hash_depends_on_every_field() {
+        let command = test_command();
+        let hash = command.hash().unwrap();
+
+        let mut other_commands = [command; 7];
+        other_commands[0].state_index += 1;
+        other_commands[1].new_public_key = test_public_key(2);
+        other_commands[2].vote_option_index += 1;
+        other_commands[3].new_vote_weight += 1;
+        other_commands[4].nonce += 1;
+        other_commands[5].poll_id += 1;
+        other_commands[6].salt = BlsScalar::from(8);
+
+        for other_command in other_commands {
+            assert_ne!(other_command.hash().unwrap(), hash);
+        }
+        assert_eq!(test_command().hash().unwrap(), hash);
+    }
+
+    #[test]
+    fn encrypt_then_decrypt_returns_command_and_signature() {
+        let command = test_command();
+        let signature = command.sign(&JubJubScalar::from(1234u64)).unwrap();
+        let shared_key = test_public_key(5).to_affine().unwrap();
+
+        let data = command.encrypt(&signature, &shared_key).unwrap();
+
+        assert_eq!(data.len(), MESSAGE_CIPHERTEXT_LENGTH);
+        assert!(data.len() <= MESSAGE_DATA_MAX_LENGTH);
+        assert_eq!(
+            Command::decrypt(&data, &shared_key),
+            Some((command, signature))
+        );
+        assert_eq!(
+            Command::decrypt(&data, &test_public_key(6).to_affine().unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn sign_then_verify_signature_succeeds() {
+        let command = test_command();
+        let keypair = Keypair::from_private_key(PrivateKey::from(JubJubScalar::from(1234u64)));
+
+        let signature = command.sign(&keypair.private_key.scalar()).unwrap();
+
+        let public_key = PublicKey::from(keypair.public_key);
+
+        assert!(command.verify_signature(&public_key, &signature));
+
+        let mut other_command = command;
+        other_command.new_vote_weight += 1;
+
+        assert!(!other_command.verify_signature(&public_key, &signature));
+    }
+}
@@ -0,0 +1,47 @@
+use dusk_jubjub::{JubJubAffine, JubJubExtended, JubJubScalar};
+
+/// Derives the ECDH shared key between a private key and another party's public key.
+/// The voter combines its ephemeral private key with the coordinator's public key,
+/// the coordinator combines its private key with the voter's ephemeral public key.
+pub fn derive_shared_key(private_key: &JubJubScalar, public_key: &JubJubAffine) -> JubJubAffine {
+    (JubJubExtended::from(*public_key) * private_key).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use dusk_bytes::Serializable;
+    use dusk_jubjub::GENERATOR_EXTENDED;
+    use hex_literal::hex;
+
+    fn public_key(private_key: &JubJubScalar) -> JubJubAffine {
+        (GENERATOR_EXTENDED * private_key).into()
+    }
+
+    #[test]
+    fn derive_shared_key_is_symmetric() {
+        let voter_private_key = JubJubScalar::from(123u64);
+        let coordinator_private_key = JubJubScalar::from(456u64);
+
+        let voter_shared_key =
+            derive_shared_key(&voter_private_key, &public_key(&coordinator_private_key));
+        let coordinator_shared_key =
+            derive_shared_key(&coordinator_private_key, &public_key(&voter_private_key));
+
+        assert_eq!(voter_shared_key, coordinator_shared_key);
+    }
+
+    #[test]
+    fn derive_shared_key_known_answer() {
+        let shared_key = derive_shared_key(
+            &JubJubScalar::from(123u64),
+            &public_key(&JubJubScalar::from(456u64)),
+        );
+
+        assert_eq!(
+            shared_key.to_bytes(),
+            hex!("a1758c71453731e5dd229ea165b82f7ff26c9eacd2068ab00cc65a7999025105")
+        );
+    }
+}
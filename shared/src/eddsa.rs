@@ -0,0 +1,207 @@
+use dusk_bls12_381::BlsScalar;
+use dusk_bytes::{Error as BytesError, Serializable};
+use dusk_jubjub::{JubJubAffine, JubJubExtended, JubJubScalar, GENERATOR_EXTENDED};
+use dusk_poseidon::sponge;
+use rand_core::{CryptoRng, RngCore};
+
+use crate::types::{PrivateKey, PublicKey};
+
+pub const SIGNATURE_SIZE: usize = 64;
+
+/// A voter's keypair. The public key is a point of JubJub, the private key its discrete logarithm.
+/// The keypair is built from its `PrivateKey`, the key displayed to the voter is the one signing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Keypair {
+    pub private_key: PrivateKey,
+    pub public_key: JubJubAffine,
+}
+
+impl Keypair {
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        Self::from_private_key(PrivateKey::new(JubJubScalar::random(rng)))
+    }
+
+    pub fn from_private_key(private_key: PrivateKey) -> Self {
+        Self {
+            private_key,
+            public_key: (GENERATOR_EXTENDED * private_key.scalar()).into(),
+        }
+    }
+
+    /// Compressed public key, as published on chain.
+    pub fn public_key_bytes(&self) -> [u8; PublicKey::SIZE] {
+        PublicKey::from(self.public_key).to_bytes()
+    }
+
+    pub fn private_key_bytes(&self) -> [u8; PrivateKey::SIZE] {
+        self.private_key.to_bytes()
+    }
+}
+
+/// EdDSA signature, the point `R` and the scalar `s` are the inputs of the in-circuit verifier.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Signature {
+    pub r: JubJubAffine,
+    pub s: JubJubScalar,
+}
+
+impl Signature {
+    /// Compressed `R` followed by `s`, both little-endian.
+    pub fn to_bytes(&self) -> [u8; SIGNATURE_SIZE] {
+        let mut bytes = [0; SIGNATURE_SIZE];
+
+        bytes[..32].copy_from_slice(&self.r.to_bytes());
+        bytes[32..].copy_from_slice(&self.s.to_bytes());
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8; SIGNATURE_SIZE]) -> Result<Self, BytesError> {
+        let mut r = [0; 32];
+        let mut s = [0; 32];
+        r.copy_from_slice(&bytes[..32]);
+        s.copy_from_slice(&bytes[32..]);
+
+        Ok(Self {
+            r: JubJubAffine::from_bytes(&r)?,
+            s: JubJubScalar::from_bytes(&s)?,
+        })
+    }
+}
+
+/// Signs a message with EdDSA over JubJub, using Poseidon as the hash function.
+/// The nonce is derived from the private key and the message, signing is therefore deterministic.
+pub fn sign(private_key: &JubJubScalar, message: &BlsScalar) -> Signature {
+    let nonce = sponge::truncated::hash(&[BlsScalar::from(*private_key), *message]);
+
+    let r: JubJubAffine = (GENERATOR_EXTENDED * nonce).into();
+    let public_key: JubJubAffine = (GENERATOR_EXTENDED * private_key).into();
+
+    let challenge = challenge(&r, &public_key, message);
+
+    Signature {
+        r,
+        s: nonce + challenge * private_key,
+    }
+}
+
+/// Verifies that `signature` is a signature of `message` by the owner of `public_key`.
+pub fn verify(public_key: &JubJubAffine, message: &BlsScalar, signature: &Signature) -> bool {
+    let public_key_extended = JubJubExtended::from(*public_key);
+    let r_extended = JubJubExtended::from(signature.r);
+
+    if !bool::from(public_key_extended.is_prime_order()) || !bool::from(r_extended.is_prime_order())
+    {
+        return false;
+    }
+
+    let challenge = challenge(&signature.r, public_key, message);
+
+    GENERATOR_EXTENDED * signature.s == r_extended + public_key_extended * challenge
+}
+
+fn challenge(r: &JubJubAffine, public_key: &JubJubAffine, message: &BlsScalar) -> JubJubScalar {
+    sponge::truncated::hash(&[
+        r.get_x(),
+        r.get_y(),
+        public_key.get_x(),
+        public_key.get_y(),
+        *message,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::string::ToString;
+    use hex_literal::hex;
+
+    fn test_keypair() -> Keypair {
+        Keypair::from_private_key(PrivateKey::from(JubJubScalar::from(1234u64)))
+    }
+
+    #[test]
+    fn sign_then_verify_succeeds() {
+        let keypair = test_keypair();
+        let message = BlsScalar::from(42);
+
+        let signature = sign(&keypair.private_key.scalar(), &message);
+
+        assert!(verify(&keypair.public_key, &message, &signature));
+    }
+
+    #[test]
+    fn verify_with_other_message_or_key_fails() {
+        let keypair = test_keypair();
+        let message = BlsScalar::from(42);
+
+        let signature = sign(&keypair.private_key.scalar(), &message);
+
+        assert!(!verify(
+            &keypair.public_key,
+            &BlsScalar::from(43),
+            &signature
+        ));
+
+        let other_keypair =
+            Keypair::from_private_key(PrivateKey::from(JubJubScalar::from(4321u64)));
+        assert!(!verify(&other_keypair.public_key, &message, &signature));
+    }
+
+    #[test]
+    fn verify_tampered_signature_fails() {
+        let keypair = test_keypair();
+        let message = BlsScalar::from(42);
+
+        let signature = sign(&keypair.private_key.scalar(), &message);
+
+        let mut tampered_s = signature;
+        tampered_s.s += JubJubScalar::one();
+        assert!(!verify(&keypair.public_key, &message, &tampered_s));
+
+        let mut tampered_r = signature;
+        tampered_r.r = (JubJubExtended::from(signature.r) + GENERATOR_EXTENDED).into();
+        assert!(!verify(&keypair.public_key, &message, &tampered_r));
+
+        let mut tampered_bytes = signature.to_bytes();
+        tampered_bytes[40] ^= 1;
+        if let Ok(tampered_signature) = Signature::from_bytes(&tampered_bytes) {
+            assert!(!verify(&keypair.public_key, &message, &tampered_signature));
+        }
+    }
+
+    #[test]
+    fn signature_bytes_round_trip() {
+        let keypair = test_keypair();
+
+        let signature = sign(&keypair.private_key.scalar(), &BlsScalar::from(42));
+
+        assert_eq!(Signature::from_bytes(&signature.to_bytes()), Ok(signature));
+    }
+
+    #[test]
+    fn keypair_from_parsed_private_key_returns_same_keypair() {
+        let keypair = test_keypair();
+
+        let private_key: PrivateKey = keypair.private_key.to_string().parse().unwrap();
+
+        assert_eq!(Keypair::from_private_key(private_key), keypair);
+    }
+
+    #[test]
+    fn sign_known_answer() {
+        let keypair = test_keypair();
+
+        let signature = sign(&keypair.private_key.scalar(), &BlsScalar::from(42));
+
+        assert_eq!(
+            keypair.public_key_bytes(),
+            hex!("a03b8da6b1ee39f8dfd2f2c3d114d59410d402a51bc9d7de0dfababb33e5d7c0")
+        );
+        assert_eq!(
+            signature.to_bytes(),
+            hex!("7528cf2c6c1e044a6e9a6bf186d624864e580e79ba74961d6ea5f28609f4aeea2c5ac0cd127f599bf5d5e87a36c4f1575cfdb09176528331861cfcc67510160d")
+        );
+    }
+}
@@ -1,6 +1,12 @@
 use dusk_bls12_381::BlsScalar;
 
-use crate::types::{PublicKey, TreeRoot};
+/// Limbs of the modulus of the BLS12-381 scalar field, least significant first.
+const SCALAR_FIELD_MODULUS: [u64; 4] = [
+    0xffff_ffff_0000_0001,
+    0x53bd_a402_fffe_5bfe,
+    0x3339_d808_09a1_d805,
+    0x73ed_a753_299d_7d48,
+];
 
 pub fn bytes_to_u64(bytes: [u8; 32]) -> [u64; 4] {
     let mut result = [0; 4];
@@ -17,20 +23,75 @@ pub fn bytes_to_scalar(bytes: [u8; 32]) -> BlsScalar {
     BlsScalar(bytes_to_u64(bytes))
 }
 
-pub fn generate_public_parameters(
-    new_tree_root: &TreeRoot,
-    public_key: &PublicKey,
-    coordinator_public_key: &PublicKey,
-) -> [u8; 96] {
-    let mut public_parameters: [u8; 96] = [0; 96];
+/// Returns whether `bytes` are the representation of a scalar of the field, i.e. whether their limbs are lower
+/// than the modulus. `bytes_to_scalar` does not reduce the limbs, hashing other bytes natively does not give
+/// the hash computed in the circuits.
+pub fn is_canonical_scalar(bytes: [u8; 32]) -> bool {
+    let limbs = bytes_to_u64(bytes);
 
-    let mut index: usize = 0;
-    public_parameters[index..new_tree_root.len()].copy_from_slice(new_tree_root);
-    index += new_tree_root.len();
-    public_parameters[index..index + public_key.len()].copy_from_slice(public_key);
-    index += public_key.len();
-    public_parameters[index..index + coordinator_public_key.len()]
-        .copy_from_slice(coordinator_public_key);
+    for (limb, modulus_limb) in limbs.iter().zip(SCALAR_FIELD_MODULUS.iter()).rev() {
+        if limb != modulus_limb {
+            return limb < modulus_limb;
+        }
+    }
+
+    false
+}
+
+pub fn scalar_to_bytes(scalar: BlsScalar) -> [u8; 32] {
+    u64_to_bytes(*scalar.internal_repr())
+}
+
+pub fn u64_to_bytes(array: [u64; 4]) -> [u8; 32] {
+    let mut result = [0; 32];
+
+    for (i, limb) in array.iter().enumerate() {
+        result[i * 8..(i + 1) * 8].copy_from_slice(&limb.to_be_bytes());
+    }
+
+    result
+}
+
+pub fn u128_to_scalar(value: u128) -> BlsScalar {
+    BlsScalar::from_raw([value as u64, (value >> 64) as u64, 0, 0])
+}
+
+#[test]
+fn u64_to_bytes_works_under_256() {
+    let array: [u64; 4] = [10, 255, 0, 3];
+
+    let result = u64_to_bytes(array);
+
+    assert_eq!(
+        [
+            0, 0, 0, 0, 0, 0, 0, 10, 0, 0, 0, 0, 0, 0, 0, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 0, 3
+        ],
+        result
+    )
+}
+
+#[test]
+fn u64_to_bytes_works_over_256() {
+    let array: [u64; 4] = [196710, 257, 0, 899];
+
+    let result = u64_to_bytes(array);
+
+    assert_eq!(
+        [
+            0, 0, 0, 0, 0, 3, 0, 102, 0, 0, 0, 0, 0, 0, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0, 0, 0, 3, 131
+        ],
+        result
+    )
+}
+
+#[test]
+fn is_canonical_scalar_rejects_limbs_over_modulus() {
+    assert!(is_canonical_scalar(scalar_to_bytes(BlsScalar::zero())));
+    assert!(is_canonical_scalar(scalar_to_bytes(-BlsScalar::one())));
+    assert!(is_canonical_scalar([2; 32]));
 
-    public_parameters
+    assert!(!is_canonical_scalar(u64_to_bytes(SCALAR_FIELD_MODULUS)));
+    assert!(!is_canonical_scalar([0xff; 32]));
 }
@@ -0,0 +1,103 @@
+use blake2::{digest::consts::U32, Blake2b, Digest};
+use core::fmt;
+
+/// Prefix of serialized public keys.
+pub const PUBLIC_KEY_PREFIX: &str = "makipk.";
+
+/// Prefix of serialized private keys.
+pub const PRIVATE_KEY_PREFIX: &str = "makisk.";
+
+/// Number of checksum bytes appended to a serialized key.
+pub const CHECKSUM_SIZE: usize = 4;
+
+const KEY_SIZE: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyParseError {
+    /// The string does not start with the expected prefix.
+    InvalidPrefix,
+    /// The string does not hold a key and its checksum.
+    InvalidLength,
+    /// The string contains a character that is not hexadecimal.
+    InvalidHex,
+    /// The checksum does not match the key, the string was probably mistyped.
+    InvalidChecksum,
+    /// The bytes are well formed but are not a valid key.
+    InvalidKey,
+}
+
+impl fmt::Display for KeyParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            KeyParseError::InvalidPrefix => "key does not start with the expected prefix",
+            KeyParseError::InvalidLength => "key does not have the expected length",
+            KeyParseError::InvalidHex => "key contains non hexadecimal characters",
+            KeyParseError::InvalidChecksum => "key checksum does not match",
+            KeyParseError::InvalidKey => "key is not valid",
+        };
+
+        f.write_str(message)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for KeyParseError {}
+
+/// Writes `prefix` followed by the hexadecimal key bytes and checksum.
+pub(crate) fn write_key(
+    f: &mut fmt::Formatter<'_>,
+    prefix: &str,
+    key: &[u8; KEY_SIZE],
+) -> fmt::Result {
+    f.write_str(prefix)?;
+
+    for byte in key.iter().chain(checksum(prefix, key).iter()) {
+        write!(f, "{:02x}", byte)?;
+    }
+
+    Ok(())
+}
+
+/// Reads the key bytes of a string written by `write_key`, checking its prefix and checksum.
+pub(crate) fn read_key(s: &str, prefix: &str) -> Result<[u8; KEY_SIZE], KeyParseError> {
+    let hex = s.strip_prefix(prefix).ok_or(KeyParseError::InvalidPrefix)?;
+
+    if hex.len() != 2 * (KEY_SIZE + CHECKSUM_SIZE) {
+        return Err(KeyParseError::InvalidLength);
+    }
+
+    let mut bytes = [0; KEY_SIZE + CHECKSUM_SIZE];
+    for (byte, digits) in bytes.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        *byte = (hex_digit(digits[0])? << 4) | hex_digit(digits[1])?;
+    }
+
+    let mut key = [0; KEY_SIZE];
+    key.copy_from_slice(&bytes[..KEY_SIZE]);
+
+    if bytes[KEY_SIZE..] != checksum(prefix, &key) {
+        return Err(KeyParseError::InvalidChecksum);
+    }
+
+    Ok(key)
+}
+
+/// First bytes of the BLAKE2b hash of the prefix and the key, so that a key can not be parsed as the other kind.
+fn checksum(prefix: &str, key: &[u8; KEY_SIZE]) -> [u8; CHECKSUM_SIZE] {
+    let mut hasher = Blake2b::<U32>::new();
+    hasher.update(prefix.as_bytes());
+    hasher.update(key);
+
+    let mut checksum = [0; CHECKSUM_SIZE];
+    checksum.copy_from_slice(&hasher.finalize()[..CHECKSUM_SIZE]);
+
+    checksum
+}
+
+fn hex_digit(digit: u8) -> Result<u8, KeyParseError> {
+    match digit {
+        b'0'..=b'9' => Ok(digit - b'0'),
+        b'a'..=b'f' => Ok(digit - b'a' + 10),
+        b'A'..=b'F' => Ok(digit - b'A' + 10),
+        _ => Err(KeyParseError::InvalidHex),
+    }
+}
@@ -1,4 +1,13 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 
+extern crate alloc;
+
+pub mod command;
+pub mod ecdh;
+pub mod eddsa;
 pub mod functions_utils;
+pub mod key_format;
+pub mod poseidon_encryption;
+pub mod private_key;
+pub mod public_key;
 pub mod types;
@@ -0,0 +1,190 @@
+use alloc::vec::Vec;
+
+use dusk_bls12_381::BlsScalar;
+use dusk_hades::{ScalarStrategy, Strategy, WIDTH};
+use dusk_jubjub::JubJubAffine;
+
+/// Number of elements absorbed by each permutation of the sponge.
+const RATE: usize = WIDTH - 1;
+
+/// Length of the ciphertext of a plaintext of `plaintext_length` elements: the plaintext is padded with zeros
+/// to a multiple of the rate and followed by an authentication tag.
+pub const fn ciphertext_length(plaintext_length: usize) -> usize {
+    plaintext_length.div_ceil(RATE) * RATE + 1
+}
+
+/// Encrypts field elements with a Poseidon duplex sponge keyed by an ECDH shared key.
+/// The nonce must never be reused with the same shared key.
+pub fn encrypt(
+    plaintext: &[BlsScalar],
+    shared_key: &JubJubAffine,
+    nonce: &BlsScalar,
+) -> Vec<BlsScalar> {
+    let mut strategy = ScalarStrategy::new();
+    let mut state = initial_state(shared_key, nonce, plaintext.len());
+
+    let mut ciphertext = Vec::with_capacity(ciphertext_length(plaintext.len()));
+
+    strategy.perm(&mut state);
+
+    for chunk in plaintext.chunks(RATE) {
+        for i in 0..RATE {
+            state[i + 1] += chunk.get(i).copied().unwrap_or_else(BlsScalar::zero);
+            ciphertext.push(state[i + 1]);
+        }
+
+        strategy.perm(&mut state);
+    }
+
+    ciphertext.push(state[1]);
+
+    ciphertext
+}
+
+/// Decrypts a ciphertext produced by `encrypt`. Returns `None` if the ciphertext was not encrypted
+/// with the same shared key, nonce and plaintext length, or was tampered with.
+pub fn decrypt(
+    ciphertext: &[BlsScalar],
+    plaintext_length: usize,
+    shared_key: &JubJubAffine,
+    nonce: &BlsScalar,
+) -> Option<Vec<BlsScalar>> {
+    if ciphertext.len() != ciphertext_length(plaintext_length) {
+        return None;
+    }
+
+    let mut strategy = ScalarStrategy::new();
+    let mut state = initial_state(shared_key, nonce, plaintext_length);
+
+    let mut plaintext = Vec::with_capacity(ciphertext.len() - 1);
+
+    strategy.perm(&mut state);
+
+    for chunk in ciphertext[..ciphertext.len() - 1].chunks(RATE) {
+        for i in 0..RATE {
+            plaintext.push(chunk[i] - state[i + 1]);
+            state[i + 1] = chunk[i];
+        }
+
+        strategy.perm(&mut state);
+    }
+
+    if ciphertext[ciphertext.len() - 1] != state[1] {
+        return None;
+    }
+
+    // The padding must decrypt to zeros
+    if plaintext[plaintext_length..]
+        .iter()
+        .any(|element| *element != BlsScalar::zero())
+    {
+        return None;
+    }
+
+    plaintext.truncate(plaintext_length);
+
+    Some(plaintext)
+}
+
+/// Domain separator of the encryption, in the capacity element, followed by the plaintext length, the key and the nonce.
+fn initial_state(
+    shared_key: &JubJubAffine,
+    nonce: &BlsScalar,
+    plaintext_length: usize,
+) -> [BlsScalar; WIDTH] {
+    [
+        BlsScalar::from_raw([0x100000000u64, 0, 0, 0]),
+        BlsScalar::from(plaintext_length as u64),
+        shared_key.get_x(),
+        shared_key.get_y(),
+        *nonce,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use dusk_bytes::Serializable;
+    use dusk_jubjub::{JubJubScalar, GENERATOR_EXTENDED};
+    use hex_literal::hex;
+
+    fn shared_key() -> JubJubAffine {
+        (GENERATOR_EXTENDED * JubJubScalar::from(789u64)).into()
+    }
+
+    fn plaintext(length: u64) -> Vec<BlsScalar> {
+        (1..=length).map(BlsScalar::from).collect()
+    }
+
+    #[test]
+    fn encrypt_then_decrypt_returns_plaintext() {
+        let nonce = BlsScalar::from(7);
+
+        for length in 0..10 {
+            let plaintext = plaintext(length);
+
+            let ciphertext = encrypt(&plaintext, &shared_key(), &nonce);
+
+            assert_eq!(ciphertext.len(), ciphertext_length(plaintext.len()));
+            assert_eq!(
+                decrypt(&ciphertext, plaintext.len(), &shared_key(), &nonce),
+                Some(plaintext)
+            );
+        }
+    }
+
+    #[test]
+    fn decrypt_with_wrong_key_or_nonce_returns_none() {
+        let nonce = BlsScalar::from(7);
+        let ciphertext = encrypt(&plaintext(5), &shared_key(), &nonce);
+
+        let other_key: JubJubAffine = (GENERATOR_EXTENDED * JubJubScalar::from(790u64)).into();
+
+        assert_eq!(decrypt(&ciphertext, 5, &other_key, &nonce), None);
+        assert_eq!(
+            decrypt(&ciphertext, 5, &shared_key(), &BlsScalar::from(8)),
+            None
+        );
+    }
+
+    #[test]
+    fn decrypt_tampered_ciphertext_returns_none() {
+        let nonce = BlsScalar::from(7);
+        let mut ciphertext = encrypt(&plaintext(5), &shared_key(), &nonce);
+
+        ciphertext[2] += BlsScalar::one();
+
+        assert_eq!(decrypt(&ciphertext, 5, &shared_key(), &nonce), None);
+    }
+
+    #[test]
+    fn decrypt_with_wrong_length_returns_none() {
+        let nonce = BlsScalar::from(7);
+        let ciphertext = encrypt(&plaintext(5), &shared_key(), &nonce);
+
+        assert_eq!(decrypt(&ciphertext, 6, &shared_key(), &nonce), None);
+        assert_eq!(decrypt(&ciphertext, 9, &shared_key(), &nonce), None);
+    }
+
+    #[test]
+    fn encrypt_known_answer() {
+        let ciphertext = encrypt(&plaintext(5), &shared_key(), &BlsScalar::from(7));
+
+        let ciphertext_bytes: Vec<[u8; 32]> = ciphertext.iter().map(|e| e.to_bytes()).collect();
+
+        let expected_ciphertext: [[u8; 32]; 9] = [
+            hex!("6a46610a208e7d38bdb8f4ccac1cad0ce1a6f9e9c2513873ce8b05650a5be072"),
+            hex!("c3ebad17103bf2b0b26fafe02e4c6aa858ee7b6ddd67db8fc842ff27991ae026"),
+            hex!("652de48bd9259e243b3694d2e19bf8c13ca1fb446c76d71a4f8d3e771204c209"),
+            hex!("432267932b5ae3128f3748553a20e02eb92bad6836ad333c33344caa36b0575c"),
+            hex!("a6d6249bc9ae92d0e588b0a0625130d58f847b00c951d398c9456e81d0c30c6a"),
+            hex!("1a350d048185aea80921d2d2980ff531669da4ce8b31f8268361fea8ad3fd429"),
+            hex!("2b24eb830adfb034510100b2174841d23099fd7631051893d027f3537b9c3a0f"),
+            hex!("11bb92ec603148dde2bcada914232db642780bb587da34617739ff8aab9f2764"),
+            hex!("64ff776f32a9d1815d641fb61a3ad222b1f0d770105679b120c6edc3d7e4d526"),
+        ];
+
+        assert_eq!(ciphertext_bytes, expected_ciphertext);
+    }
+}
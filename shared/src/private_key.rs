@@ -0,0 +1,120 @@
+use core::{fmt, str::FromStr};
+
+use dusk_bytes::Serializable;
+use dusk_jubjub::{JubJubAffine, JubJubScalar, GENERATOR_EXTENDED};
+
+use crate::key_format::{self, KeyParseError, PRIVATE_KEY_PREFIX};
+use crate::public_key::PublicKey;
+
+/// Private key of a user or of a coordinator, a scalar of JubJub.
+///
+/// Displayed as `makisk.` followed by the hexadecimal key and checksum, `Debug` does not print the key.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PrivateKey(JubJubScalar);
+
+impl PrivateKey {
+    pub const SIZE: usize = 32;
+
+    pub const fn new(scalar: JubJubScalar) -> Self {
+        Self(scalar)
+    }
+
+    pub fn scalar(&self) -> JubJubScalar {
+        self.0
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::from(JubJubAffine::from(GENERATOR_EXTENDED * self.0))
+    }
+
+    /// Canonical little-endian bytes of the scalar.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        self.0.to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8; Self::SIZE]) -> Option<Self> {
+        JubJubScalar::from_bytes(bytes).ok().map(Self)
+    }
+}
+
+impl From<JubJubScalar> for PrivateKey {
+    fn from(scalar: JubJubScalar) -> Self {
+        Self(scalar)
+    }
+}
+
+impl fmt::Debug for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("PrivateKey(..)")
+    }
+}
+
+impl fmt::Display for PrivateKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        key_format::write_key(f, PRIVATE_KEY_PREFIX, &self.to_bytes())
+    }
+}
+
+impl FromStr for PrivateKey {
+    type Err = KeyParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = key_format::read_key(s, PRIVATE_KEY_PREFIX)?;
+
+        Self::from_bytes(&bytes).ok_or(KeyParseError::InvalidKey)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::string::ToString;
+
+    fn test_private_key() -> PrivateKey {
+        PrivateKey::from(JubJubScalar::from(1234u64))
+    }
+
+    #[test]
+    fn to_string_then_parse_returns_same_private_key() {
+        let private_key = test_private_key();
+
+        let serialized = private_key.to_string();
+
+        assert!(serialized.starts_with(PRIVATE_KEY_PREFIX));
+        assert_eq!(serialized.parse(), Ok(private_key));
+    }
+
+    #[test]
+    fn parse_public_key_string_returns_error() {
+        let serialized = test_private_key().public_key().to_string();
+
+        assert_eq!(
+            serialized.parse::<PrivateKey>(),
+            Err(KeyParseError::InvalidPrefix)
+        );
+    }
+
+    #[test]
+    fn parse_non_canonical_scalar_returns_error() {
+        struct Key([u8; 32]);
+
+        impl fmt::Display for Key {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                key_format::write_key(f, PRIVATE_KEY_PREFIX, &self.0)
+            }
+        }
+
+        let serialized = Key([255; 32]).to_string();
+
+        assert_eq!(
+            serialized.parse::<PrivateKey>(),
+            Err(KeyParseError::InvalidKey)
+        );
+    }
+
+    #[test]
+    fn debug_does_not_print_the_key() {
+        assert_eq!(alloc::format!("{:?}", test_private_key()), "PrivateKey(..)");
+    }
+}
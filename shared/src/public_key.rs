@@ -0,0 +1,257 @@
+use core::{fmt, str::FromStr};
+
+use dusk_bls12_381::BlsScalar;
+use dusk_bytes::Serializable;
+use dusk_jubjub::JubJubAffine;
+
+use crate::functions_utils::{bytes_to_scalar, scalar_to_bytes};
+use crate::key_format::{self, KeyParseError, PUBLIC_KEY_PREFIX};
+
+/// Public key of a user or of a coordinator, a point of JubJub.
+///
+/// Public keys are SCALE encoded as their coordinates, in the 32 bytes form of the other scalars. Any bytes are
+/// decoded, whether the point is on the curve and in the prime order subgroup must be checked with `is_valid`.
+/// Displayed as `makipk.` followed by the hexadecimal 32 bytes compressed key and checksum.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PublicKey {
+    pub x: BlsScalar,
+    pub y: BlsScalar,
+}
+
+impl PublicKey {
+    pub const SIZE: usize = 32;
+
+    /// Size of the SCALE encoding, the coordinates being encoded separately.
+    pub const ENCODED_SIZE: usize = 64;
+
+    pub const fn new(x: BlsScalar, y: BlsScalar) -> Self {
+        Self { x, y }
+    }
+
+    /// Compressed form, the `y` coordinate with the sign of `x` in its most significant bit.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        JubJubAffine::from_raw_unchecked(self.x, self.y).to_bytes()
+    }
+
+    pub fn from_bytes(bytes: &[u8; Self::SIZE]) -> Option<Self> {
+        JubJubAffine::from_bytes(bytes).ok().map(Self::from)
+    }
+
+    /// Returns the point if it is on the curve and in the prime order subgroup.
+    pub fn to_affine(&self) -> Option<JubJubAffine> {
+        JubJubAffine::from_bytes(&self.to_bytes())
+            .ok()
+            .filter(|point| point.get_x() == self.x && point.get_y() == self.y)
+            .filter(|point| bool::from(point.is_prime_order()))
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.to_affine().is_some()
+    }
+}
+
+impl From<JubJubAffine> for PublicKey {
+    fn from(point: JubJubAffine) -> Self {
+        Self::new(point.get_x(), point.get_y())
+    }
+}
+
+impl fmt::Display for PublicKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        key_format::write_key(f, PUBLIC_KEY_PREFIX, &self.to_bytes())
+    }
+}
+
+impl FromStr for PublicKey {
+    type Err = KeyParseError;
+
+    /// Parses a key displayed with `Display`, the key must be valid.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = key_format::read_key(s, PUBLIC_KEY_PREFIX)?;
+
+        Self::from_bytes(&bytes)
+            .filter(Self::is_valid)
+            .ok_or(KeyParseError::InvalidKey)
+    }
+}
+
+impl scale::Encode for PublicKey {
+    fn size_hint(&self) -> usize {
+        Self::ENCODED_SIZE
+    }
+
+    fn encode_to<T: scale::Output + ?Sized>(&self, dest: &mut T) {
+        dest.write(&scalar_to_bytes(self.x));
+        dest.write(&scalar_to_bytes(self.y));
+    }
+}
+
+impl scale::Decode for PublicKey {
+    /// Decodes the coordinates unchecked, so that an invalid key can be rejected with `is_valid`.
+    fn decode<I: scale::Input>(input: &mut I) -> Result<Self, scale::Error> {
+        let x = <[u8; 32]>::decode(input)?;
+        let y = <[u8; 32]>::decode(input)?;
+
+        Ok(Self::new(bytes_to_scalar(x), bytes_to_scalar(y)))
+    }
+}
+
+#[cfg(feature = "std")]
+impl scale_info::TypeInfo for PublicKey {
+    type Identity = Self;
+
+    fn type_info() -> scale_info::Type {
+        scale_info::Type::builder()
+            .path(scale_info::Path::new("PublicKey", module_path!()))
+            .composite(
+                scale_info::build::Fields::named()
+                    .field(|f| f.ty::<[u8; 32]>().name("x"))
+                    .field(|f| f.ty::<[u8; 32]>().name("y")),
+            )
+    }
+}
+
+#[cfg(feature = "std")]
+impl ink::storage::traits::StorageLayout for PublicKey {
+    fn layout(key: &ink::primitives::Key) -> ink::metadata::layout::Layout {
+        ink::metadata::layout::Layout::Leaf(ink::metadata::layout::LeafLayout::from_key::<Self>(
+            ink::metadata::layout::LayoutKey::from(key),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::string::{String, ToString};
+    use dusk_jubjub::{JubJubExtended, JubJubScalar, GENERATOR_EXTENDED};
+    use scale::{Decode, Encode};
+
+    fn test_public_key() -> PublicKey {
+        PublicKey::from(JubJubAffine::from(
+            GENERATOR_EXTENDED * JubJubScalar::from(1234u64),
+        ))
+    }
+
+    #[test]
+    fn encode_then_decode_returns_same_public_key() {
+        let public_key = test_public_key();
+
+        let encoded = public_key.encode();
+
+        assert_eq!(encoded.len(), PublicKey::ENCODED_SIZE);
+        assert_eq!(PublicKey::decode(&mut &encoded[..]), Ok(public_key));
+    }
+
+    #[test]
+    fn decode_point_not_on_curve_returns_invalid_key() {
+        let mut public_key = test_public_key();
+        public_key.x += BlsScalar::one();
+
+        let decoded = PublicKey::decode(&mut &public_key.encode()[..]).unwrap();
+
+        assert_eq!(decoded, public_key);
+        assert!(!decoded.is_valid());
+    }
+
+    #[test]
+    fn decode_coordinates_out_of_scalar_field_returns_invalid_key() {
+        let decoded = PublicKey::decode(&mut &[0xff; PublicKey::ENCODED_SIZE][..]).unwrap();
+
+        assert!(!decoded.is_valid());
+    }
+
+    #[test]
+    fn is_valid_accepts_prime_order_points() {
+        assert!(test_public_key().is_valid());
+    }
+
+    #[test]
+    fn is_valid_rejects_points_not_on_curve() {
+        let mut public_key = test_public_key();
+        public_key.x += BlsScalar::one();
+
+        assert!(!public_key.is_valid());
+    }
+
+    #[test]
+    fn is_valid_rejects_points_outside_prime_order_subgroup() {
+        let identity = PublicKey::from(JubJubAffine::identity());
+
+        // (0, -1) is on the curve and of order 2
+        let low_order_point = PublicKey::new(BlsScalar::zero(), -BlsScalar::one());
+
+        let mixed_order_point = PublicKey::from(JubJubAffine::from(
+            JubJubExtended::from(test_public_key().to_affine().unwrap())
+                + JubJubExtended::from(JubJubAffine::from_raw_unchecked(
+                    BlsScalar::zero(),
+                    -BlsScalar::one(),
+                )),
+        ));
+
+        assert!(!identity.is_valid());
+        assert!(!low_order_point.is_valid());
+        assert!(!mixed_order_point.is_valid());
+        assert!(PublicKey::from_bytes(&mixed_order_point.to_bytes()).is_some());
+    }
+
+    #[test]
+    fn to_string_then_parse_returns_same_public_key() {
+        let public_key = test_public_key();
+
+        let serialized = public_key.to_string();
+
+        assert!(serialized.starts_with(PUBLIC_KEY_PREFIX));
+        assert_eq!(serialized.parse(), Ok(public_key));
+    }
+
+    #[test]
+    fn to_string_known_answer() {
+        assert_eq!(
+            test_public_key().to_string(),
+            "makipk.a03b8da6b1ee39f8dfd2f2c3d114d59410d402a51bc9d7de0dfababb33e5d7c088e5c579"
+        );
+    }
+
+    #[test]
+    fn parse_mistyped_key_returns_error() {
+        let serialized = test_public_key().to_string();
+
+        // Change a single digit of the key
+        let mut mistyped = String::from(&serialized[..10]);
+        mistyped.push(if &serialized[10..11] == "0" { '1' } else { '0' });
+        mistyped.push_str(&serialized[11..]);
+
+        assert_eq!(
+            mistyped.parse::<PublicKey>(),
+            Err(KeyParseError::InvalidChecksum)
+        );
+    }
+
+    #[test]
+    fn parse_malformed_string_returns_error() {
+        let serialized = test_public_key().to_string();
+        let hex = &serialized[PUBLIC_KEY_PREFIX.len()..];
+
+        assert_eq!(hex.parse::<PublicKey>(), Err(KeyParseError::InvalidPrefix));
+        assert_eq!(
+            serialized[..serialized.len() - 2].parse::<PublicKey>(),
+            Err(KeyParseError::InvalidLength)
+        );
+        assert_eq!(
+            (String::from(PUBLIC_KEY_PREFIX) + "zz" + &hex[2..]).parse::<PublicKey>(),
+            Err(KeyParseError::InvalidHex)
+        );
+    }
+
+    #[test]
+    fn parse_identity_returns_error() {
+        let serialized = PublicKey::from(JubJubAffine::identity()).to_string();
+
+        assert_eq!(
+            serialized.parse::<PublicKey>(),
+            Err(KeyParseError::InvalidKey)
+        );
+    }
+}
@@ -4,9 +4,8 @@ pub use dusk_plonk::prelude::Proof;
 pub type PoseidonHash = [u8; 32];
 pub type SerializedProof = [u8; Proof::SIZE];
 
-//TODO : change this to struct that is composed of two fields X and Y
-pub type PublicKey = [u8; 32];
+pub use crate::public_key::PublicKey;
 
-pub type PrivateKey = [u8; 32];
+pub use crate::private_key::PrivateKey;
 
 pub type TreeRoot = [u8; 32];
\ No newline at end of file